@@ -0,0 +1,340 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// How the difficulty level is adjusted during a session
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AdaptiveMethod {
+    /// The ball keeps its configured difficulty for the whole session
+    #[default]
+    Off,
+    /// The level steps up while the recent error is at or below the target, and down while it is
+    /// above it
+    Staircase,
+    /// The level is set by a PID controller on how far the recent error is from the target
+    Pid,
+}
+
+/// What the difficulty level changes about the ball
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdaptiveParameter {
+    /// The ball's speed is multiplied by the level
+    #[default]
+    Velocity,
+    /// The time between the ball's changes of direction is divided by the level
+    DirectionChanges,
+    /// Both the ball's speed and how often it changes direction
+    Both,
+}
+
+/// Settings for adapting the tracking task's difficulty to how well the participant is tracking
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ConfigAdaptive {
+    /// [`AdaptiveMethod`]
+    pub method: AdaptiveMethod,
+
+    /// [`AdaptiveParameter`]
+    pub adjusts: AdaptiveParameter,
+
+    /// The root-mean-square distance from the crosshair that the level is adjusted towards, where
+    /// 1.0 is half the width of the tracking frame
+    pub target_error: f32,
+
+    /// How many seconds of the most recent tracking error are compared with the target
+    pub window: f32,
+
+    /// How many seconds there are between adjustments of the level
+    pub update_interval: f32,
+
+    /// The level at the start of the session, where 1.0 is the ball's configured difficulty
+    pub start_level: f32,
+    pub min_level: f32,
+    pub max_level: f32,
+
+    /// How much the staircase raises the level by when the error is at or below the target
+    pub step_up: f32,
+    /// How much the staircase lowers the level by when the error is above the target
+    pub step_down: f32,
+
+    /// The PID controller's gains, for an input of the target error minus the recent error
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+impl Default for ConfigAdaptive {
+    fn default() -> Self {
+        Self {
+            method: AdaptiveMethod::Off,
+            adjusts: AdaptiveParameter::Velocity,
+            target_error: 0.15,
+            window: 2.0,
+            update_interval: 0.5,
+            start_level: 1.0,
+            min_level: 0.25,
+            max_level: 4.0,
+            step_up: 0.05,
+            step_down: 0.05,
+            kp: 5.0,
+            ki: 1.0,
+            kd: 0.0,
+        }
+    }
+}
+
+/// Adjusts a difficulty level from the recent tracking error
+pub struct AdaptiveDifficulty {
+    config: ConfigAdaptive,
+    level: f32,
+
+    /// The squared distance from the crosshair, and how many seconds it lasted, for each sample
+    /// in the window, oldest first
+    samples: VecDeque<(f32, f32)>,
+    /// The length of the samples in the window
+    window_duration: f64,
+    /// The time integral of the squared distance over the window
+    window_squared_error: f64,
+    /// Seconds since the level was last adjusted
+    since_update: f32,
+
+    /// The time integral of the PID controller's input
+    integral: f32,
+    /// The PID controller's input at the last adjustment
+    last_input: Option<f32>,
+}
+
+impl AdaptiveDifficulty {
+    pub fn new(config: ConfigAdaptive) -> Self {
+        Self {
+            config,
+            level: config.start_level,
+            samples: VecDeque::new(),
+            window_duration: 0.0,
+            window_squared_error: 0.0,
+            since_update: 0.0,
+            integral: 0.0,
+            last_input: None,
+        }
+    }
+
+    /// If the level changes during the session
+    pub fn is_enabled(&self) -> bool {
+        self.config.method != AdaptiveMethod::Off
+    }
+
+    /// The current difficulty level
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// What the ball's speed and the rate of its changes of direction are multiplied by at the
+    /// current level
+    pub fn scales(&self) -> (f32, f32) {
+        match self.config.adjusts {
+            AdaptiveParameter::Velocity => (self.level, 1.0),
+            AdaptiveParameter::DirectionChanges => (1.0, self.level),
+            AdaptiveParameter::Both => (self.level, self.level),
+        }
+    }
+
+    /// Adds a sample of the ball's squared distance from the crosshair, which lasted `delta_time`
+    /// seconds, and adjusts the level if it is due
+    pub fn add(&mut self, squared_error: f32, delta_time: f32) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.samples.push_back((squared_error, delta_time));
+        self.window_duration += delta_time as f64;
+        self.window_squared_error += (squared_error * delta_time) as f64;
+
+        // The oldest samples are dropped once the rest still fill the window
+        while let Some(&(oldest_error, oldest_time)) = self.samples.front() {
+            if self.window_duration - (oldest_time as f64) < self.config.window as f64 {
+                break;
+            }
+
+            self.samples.pop_front();
+            self.window_duration -= oldest_time as f64;
+            self.window_squared_error -= (oldest_error * oldest_time) as f64;
+        }
+
+        self.since_update += delta_time;
+
+        if self.since_update >= self.config.update_interval {
+            let interval = std::mem::take(&mut self.since_update);
+            let recent_error = (self.window_squared_error.max(0.0) / self.window_duration).sqrt();
+
+            self.adjust(recent_error as f32, interval);
+        }
+    }
+
+    /// Moves the level based on the recent root-mean-square error, `interval` seconds after the
+    /// last adjustment
+    fn adjust(&mut self, recent_error: f32, interval: f32) {
+        let config = &self.config;
+
+        let level = match config.method {
+            AdaptiveMethod::Off => return,
+            AdaptiveMethod::Staircase => {
+                if recent_error <= config.target_error {
+                    self.level + config.step_up
+                } else {
+                    self.level - config.step_down
+                }
+            }
+            AdaptiveMethod::Pid => {
+                // Positive when the participant is tracking better than the target, so the task
+                // should get harder
+                let input = config.target_error - recent_error;
+                let integral = self.integral + input * interval;
+                let derivative = self
+                    .last_input
+                    .map_or(0.0, |last| (input - last) / interval);
+                self.last_input = Some(input);
+
+                let level = config.start_level
+                    + config.kp * input
+                    + config.ki * integral
+                    + config.kd * derivative;
+
+                // The integral stops growing while the level is held at a limit, so that it
+                // doesn't take a long time to come back from it
+                if (config.min_level..=config.max_level).contains(&level) {
+                    self.integral = integral;
+                }
+
+                level
+            }
+        };
+
+        self.level = level.clamp(config.min_level, config.max_level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sample length that adds up exactly to the update interval and window
+    const DELTA_TIME: f32 = 0.125;
+
+    fn adaptive(method: AdaptiveMethod) -> AdaptiveDifficulty {
+        AdaptiveDifficulty::new(ConfigAdaptive {
+            method,
+            ..Default::default()
+        })
+    }
+
+    /// Tracks with a steady `error` for `seconds`
+    fn track(adaptive: &mut AdaptiveDifficulty, error: f32, seconds: f32) {
+        for _ in 0..(seconds / DELTA_TIME).round() as usize {
+            adaptive.add(error * error, DELTA_TIME);
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "the level is {actual}, not {expected}"
+        );
+    }
+
+    #[test]
+    fn the_level_stays_put_when_adapting_is_off() {
+        let mut adaptive = adaptive(AdaptiveMethod::Off);
+        track(&mut adaptive, 0.0, 10.0);
+
+        assert!(!adaptive.is_enabled());
+        assert_eq!(adaptive.level(), 1.0);
+    }
+
+    #[test]
+    fn the_staircase_steps_once_per_update_interval() {
+        let mut improving = adaptive(AdaptiveMethod::Staircase);
+
+        track(&mut improving, 0.0, 0.375);
+        assert_eq!(improving.level(), 1.0, "it isn't time to adjust yet");
+        track(&mut improving, 0.0, 0.125);
+        assert_close(improving.level(), 1.05);
+        track(&mut improving, 0.0, 1.0);
+        assert_close(improving.level(), 1.15);
+
+        let mut struggling = adaptive(AdaptiveMethod::Staircase);
+        track(&mut struggling, 0.5, 1.0);
+        assert_close(struggling.level(), 0.9);
+    }
+
+    #[test]
+    fn the_staircase_stays_within_the_limits() {
+        let mut adaptive = adaptive(AdaptiveMethod::Staircase);
+        track(&mut adaptive, 0.0, 100.0);
+        assert_eq!(adaptive.level(), 4.0);
+
+        track(&mut adaptive, 1.0, 100.0);
+        assert_eq!(adaptive.level(), 0.25);
+    }
+
+    #[test]
+    fn old_errors_leave_the_window() {
+        let mut adaptive = adaptive(AdaptiveMethod::Staircase);
+        track(&mut adaptive, 1.0, 10.0);
+
+        // Half a second of the large error is still in the two second window
+        track(&mut adaptive, 0.0, 1.5);
+        assert_eq!(adaptive.level(), 0.25);
+        track(&mut adaptive, 0.0, 0.5);
+        assert_close(adaptive.level(), 0.3);
+    }
+
+    #[test]
+    fn the_pid_controller_stays_within_the_limits() {
+        let mut adaptive = adaptive(AdaptiveMethod::Pid);
+        for _ in 0..200 {
+            track(&mut adaptive, 0.0, 0.5);
+            assert!((0.25..=4.0).contains(&adaptive.level()));
+        }
+        assert_eq!(adaptive.level(), 4.0);
+
+        for _ in 0..200 {
+            track(&mut adaptive, 1.0, 0.5);
+            assert!((0.25..=4.0).contains(&adaptive.level()));
+        }
+        assert_eq!(adaptive.level(), 0.25);
+    }
+
+    #[test]
+    fn the_pid_integral_doesnt_wind_up_at_a_limit() {
+        let mut adaptive = adaptive(AdaptiveMethod::Pid);
+        track(&mut adaptive, 0.0, 100.0);
+        assert_eq!(adaptive.level(), 4.0);
+
+        // Tracking at the target leaves only the integral term, which stopped growing once the
+        // level reached the maximum
+        track(&mut adaptive, 0.15, 3.0);
+        assert!(
+            adaptive.level() < 4.0,
+            "the level is still held at the maximum"
+        );
+    }
+
+    #[test]
+    fn the_level_scales_the_adjusted_parameter() {
+        let mut config = ConfigAdaptive {
+            start_level: 2.0,
+            ..Default::default()
+        };
+
+        for (adjusts, scales) in [
+            (AdaptiveParameter::Velocity, (2.0, 1.0)),
+            (AdaptiveParameter::DirectionChanges, (1.0, 2.0)),
+            (AdaptiveParameter::Both, (2.0, 2.0)),
+        ] {
+            config.adjusts = adjusts;
+            assert_eq!(AdaptiveDifficulty::new(config).scales(), scales);
+        }
+    }
+}
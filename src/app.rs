@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Mutex};
+use std::{collections::HashMap, sync::mpsc::Sender, time::Instant};
 
 use eframe::{
     egui::{self, Frame, Key},
@@ -6,88 +6,65 @@ use eframe::{
     epaint::Color32,
 };
 
-use crate::config::{ConfigAlarm, ConfigTrial};
 use crate::{
-    ball::Ball,
-    config::InputMode,
-    dial::Dial,
+    config::{ConfigMouse, InputMode, MouseControl},
     dial_widget::{
         DialWidget, DIALS_HEIGHT_PERCENT, MAX_DIALS_WIDTH_PERCENT, MAX_DIAL_HEIGHT_PERCENT,
     },
-    output::SessionOutput,
-    tracking_widget::{TrackingWidget, TrackingWidgetState},
+    keyboard::ConfigKeyboard,
+    model::{InputEvent, Snapshot, SnapshotCell},
+    response::{ResponseEvent, ResponseInput},
+    tracking_widget::{TrackingView, TrackingWidget},
     DEFAULT_OUTPUT_PATH,
 };
 
 const UI_BACKGROUND_COLOR: Color32 = Color32::from_rgb(27, 27, 27);
 
-// We don't really need extra indirection by Box-ing RunningState, we aren't moving a bunch
-// of AppState's around all the time
-#[allow(clippy::large_enum_variant)]
-pub enum AppState {
-    Running(RunningState),
-    Done,
-}
-
-impl Default for AppState {
-    fn default() -> Self {
-        AppState::Running(RunningState::new())
-    }
-}
-
-pub struct RunningState {
-    pub dial_rows: Vec<Vec<Dial>>,
-    pub trials: Vec<ConfigTrial>,
-    pub alarms: HashMap<String, ConfigAlarm>,
-    pub ball: Ball,
-    pub alarm_active: bool,
-    pub current_trial_number: usize,
-    /// The input axes as stored as [-1.0 to 1.0, -1.0 to 1.0]: [x, y]
-    pub input_axes: Vec2,
+pub struct DialsApp {
+    /// Where the model publishes what should be drawn
+    snapshots: SnapshotCell,
+    /// Where input is sent to the model
+    inputs: Sender<InputEvent>,
+    input_mode: InputMode,
+    keyboard: ConfigKeyboard,
+    mouse: ConfigMouse,
+    tracking_view: TrackingView,
+    /// Whether each key was held down as of the last key event
+    last_keys: HashMap<Key, bool>,
     /// The input axes as stored as [0.0 to 1.0, 0.0 to 1.0]
-    pub input_x: [f32; 2],
-    pub input_y: [f32; 2],
-    /// If a key was recently pressed which is to be interpreted as an alarm reaction
-    pub pressed_key: Option<char>,
-    pub last_keys: HashMap<Key, bool>,
-    pub input_mode: InputMode,
-    pub session_output: SessionOutput,
-    pub tracking_state: TrackingWidgetState,
+    input_x: [f32; 2],
+    input_y: [f32; 2],
+    /// Where the tracking frame was last drawn on the screen
+    tracking_rect: Option<egui::Rect>,
 }
 
-impl RunningState {
-    pub fn new() -> Self {
+impl DialsApp {
+    pub fn new(
+        ctx: &egui::Context,
+        snapshots: SnapshotCell,
+        inputs: Sender<InputEvent>,
+        input_mode: InputMode,
+        keyboard: ConfigKeyboard,
+        mouse: ConfigMouse,
+        tracking_view: TrackingView,
+    ) -> Self {
+        DialsApp::style(ctx);
+
         Self {
-            dial_rows: Vec::new(),
-            trials: Vec::new(),
-            alarms: HashMap::new(),
-            ball: Ball::new(0.0, 0.0, crate::ball::BallVelocity::Slow),
-            alarm_active: false,
-            current_trial_number: 1,
-            input_axes: Vec2::ZERO,
+            snapshots,
+            inputs,
+            input_mode,
+            keyboard,
+            mouse,
+            tracking_view,
+            last_keys: HashMap::new(),
             input_x: [0.0, 0.0],
             input_y: [0.0, 0.0],
-            pressed_key: None,
-            last_keys: HashMap::new(),
-            input_mode: InputMode::default(),
-            session_output: SessionOutput::new(String::new()),
-            tracking_state: TrackingWidgetState::new(false, None, 0.0, Color32::WHITE),
+            tracking_rect: None,
         }
     }
-}
 
-pub struct DialsApp {
-    state_mutex: &'static Mutex<AppState>,
-}
-
-impl DialsApp {
-    pub fn new(cc: &eframe::CreationContext, state_mutex: &'static Mutex<AppState>) -> Self {
-        DialsApp::style(cc);
-
-        Self { state_mutex }
-    }
-
-    fn style(cc: &eframe::CreationContext) {
+    fn style(ctx: &egui::Context) {
         let mut style = egui::style::Style::default();
 
         style.visuals = egui::style::Visuals::dark();
@@ -96,20 +73,115 @@ impl DialsApp {
             font_id.size = 72.0;
         }
 
-        cc.egui_ctx.set_style(style);
+        ctx.set_style(style);
     }
 
     pub fn ui(&mut self, ctx: &egui::Context) {
-        let state = self.state_mutex.lock().unwrap();
+        let snapshot = self.snapshots.latest();
+
+        if snapshot.done {
+            self.done_ui(ctx);
+        } else {
+            self.dial_ui(ctx, &snapshot);
+            self.tracking_ui(ctx, &snapshot);
+        }
+    }
+
+    /// Draws a frame, and reads the mouse if it moves the ball
+    pub fn update(&mut self, ctx: &egui::Context) {
+        self.ui(ctx);
+
+        if let InputMode::Mouse = self.input_mode {
+            self.mouse_input(ctx);
+        }
+    }
+
+    /// Handles the events that one input event from the window was turned into, which arrived
+    /// at `received`
+    ///
+    /// This is called as soon as the window receives each event, rather than once per frame, so
+    /// key presses are timed from when they happened and the tracking keys take effect straight
+    /// away.
+    pub fn on_events(&mut self, events: &[egui::Event], received: Instant) {
+        let (mut input_x, mut input_y) = (self.input_x, self.input_y);
+
+        for event in events {
+            if let &egui::Event::Key {
+                key,
+                pressed,
+                modifiers: _,
+                repeat: _,
+            } = event
+            {
+                let last_pressed = { *self.last_keys.entry(key).or_insert(false) };
+                let value = if pressed { 1.0 } else { 0.0 };
+                // true if the this key changed from last time
+                // (was just pressed or released since the last event)
+                let key_changed = pressed != last_pressed;
+
+                // The tracking keys only move the ball when using keyboard input, otherwise
+                // they can be used to respond to alarms like any other key
+                let tracking_keys = matches!(self.input_mode, InputMode::Keyboard);
+                let keyboard = &self.keyboard;
+
+                match ResponseInput::Key(key) {
+                    k if tracking_keys && k == keyboard.up => input_y[0] = value,
+                    k if tracking_keys && k == keyboard.down => input_y[1] = value,
+                    k if tracking_keys && k == keyboard.right => input_x[0] = value,
+                    k if tracking_keys && k == keyboard.left => input_x[1] = value,
+                    k => {
+                        if key_changed && pressed {
+                            self.send(InputEvent::Response(ResponseEvent::new(k, received)));
+                        } else if key_changed {
+                            self.send(InputEvent::ResponseReleased(k));
+                        }
+                    }
+                }
 
-        match &*state {
-            AppState::Running(running_state) => {
-                self.dial_ui(ctx, running_state);
-                self.tracking_ui(ctx, running_state);
+                self.last_keys.insert(key, pressed);
             }
-            AppState::Done => {
-                self.done_ui(ctx);
+        }
+
+        if (input_x, input_y) != (self.input_x, self.input_y) {
+            let input_axes = Vec2::new(input_x[0] - input_x[1], input_y[0] - input_y[1]);
+            self.send(InputEvent::KeyboardAxes(input_axes));
+
+            self.input_x = input_x;
+            self.input_y = input_y;
+        }
+    }
+
+    /// Sends an input event to the model
+    fn send(&self, event: InputEvent) {
+        // This only fails if the model has stopped, in which case there is nothing to control
+        let _ = self.inputs.send(event);
+    }
+
+    /// Reads the mouse pointer's position over the tracking frame
+    fn mouse_input(&mut self, ctx: &egui::Context) {
+        if self.mouse.hide_cursor {
+            ctx.output_mut(|output| output.cursor_icon = egui::CursorIcon::None);
+        }
+
+        match (self.tracking_rect, ctx.pointer_hover_pos()) {
+            (Some(rect), Some(pointer)) => {
+                // Converts to the ball's coordinate system, where the frame goes from -1.0 to 1.0
+                // and positive y is down. Positions outside of the frame are kept to its edges
+                let pointer_pos = ((pointer - rect.center()) / (rect.width() / 2.0))
+                    .clamp(Vec2::splat(-1.0), Vec2::splat(1.0));
+
+                // Positive y is up for input axes
+                self.send(InputEvent::MouseAxes(Vec2::new(
+                    pointer_pos.x,
+                    -pointer_pos.y,
+                )));
             }
+            _ if self.mouse.control == MouseControl::Velocity => {
+                // The pointer left the window, so it shouldn't keep pushing the ball
+                self.send(InputEvent::MouseAxes(Vec2::ZERO));
+            }
+            // The ball stays where the pointer left the window
+            _ => {}
         }
     }
 
@@ -129,7 +201,7 @@ impl DialsApp {
     }
 
     /// Draws the tracking task part of the UI
-    fn tracking_ui(&mut self, ctx: &egui::Context, running_state: &RunningState) {
+    fn tracking_ui(&mut self, ctx: &egui::Context, snapshot: &Snapshot) {
         let window_height = ctx.available_rect().height();
 
         egui::CentralPanel::default()
@@ -137,25 +209,30 @@ impl DialsApp {
             .show(ctx, |ui| {
                 ui.vertical_centered(|ui| {
                     ui.add_space(window_height * 0.1);
-                    TrackingWidget::new(
-                        running_state.ball.pos(),
-                        running_state.tracking_state.key_detected,
-                        running_state.tracking_state.feedback_text.clone(),
-                        running_state.tracking_state.outline_color,
+                    let response = TrackingWidget::new(
+                        snapshot.ball_pos,
+                        snapshot.target_pos,
+                        snapshot.key_detected,
+                        snapshot.feedback_text.clone(),
+                        snapshot.outline_color,
+                        snapshot.paused.clone(),
+                        self.tracking_view,
                     )
                     .show(ui);
+
+                    self.tracking_rect = Some(response.rect);
                 });
             });
     }
 
     /// Draws the dials part of the UI
-    fn dial_ui(&mut self, ctx: &egui::Context, running_state: &RunningState) {
+    fn dial_ui(&mut self, ctx: &egui::Context, snapshot: &Snapshot) {
         let window_rect = ctx.available_rect();
         let window_height = window_rect.height();
         let window_width = window_rect.width();
         let bottom_panel_height = window_height * DIALS_HEIGHT_PERCENT;
 
-        let num_rows_f = running_state.dial_rows.len() as f32;
+        let num_rows_f = snapshot.dial_rows.len() as f32;
 
         let x_spacing = window_width * 0.05;
 
@@ -164,12 +241,8 @@ impl DialsApp {
             .frame(Frame::none().fill(UI_BACKGROUND_COLOR))
             .show_separator_line(false)
             .show(ctx, |ui| {
-                let max_num_dials = running_state
-                    .dial_rows
-                    .iter()
-                    .map(|r| r.len())
-                    .max()
-                    .unwrap() as f32;
+                let max_num_dials =
+                    snapshot.dial_rows.iter().map(|r| r.len()).max().unwrap() as f32;
 
                 // We want there to be at least a little space in between dial rows
                 let min_y_spacing = bottom_panel_height * 0.1;
@@ -197,7 +270,7 @@ impl DialsApp {
                 ui.add_space(y_spacing);
 
                 ui.vertical_centered_justified(|ui| {
-                    for row in &running_state.dial_rows {
+                    for row in &snapshot.dial_rows {
                         let num_dials = row.len();
 
                         let items_width =
@@ -213,10 +286,11 @@ impl DialsApp {
 
                             for dial in row.iter() {
                                 DialWidget::new(
-                                    dial.value(),
+                                    dial.value,
                                     dial_radius,
-                                    dial.in_range(),
-                                    dial.is_flashing(),
+                                    dial.in_range,
+                                    dial.flashing,
+                                    dial.label.as_deref(),
                                 )
                                 .show(ui);
                             }
@@ -229,82 +303,126 @@ impl DialsApp {
     }
 }
 
-/// Map a key press `k` to the `char` it corresponds with
-macro_rules! key_to_char {
-    ($k:expr, $($case:path, $lit:literal),+) => {
-        match $k {
-            $($case => Some($lit),)+
-            _ => None,
-        }
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::{self, Receiver},
+        time::Duration,
     };
-}
 
-impl eframe::App for DialsApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Draw the UI
-        self.ui(ctx);
+    use eframe::epaint::Pos2;
 
-        let mut state = self.state_mutex.lock().unwrap();
-
-        match &mut *state {
-            AppState::Running(state) => {
-                let (mut input_x, mut input_y) = { (state.input_x, state.input_y) };
-
-                let mut pressed_key = None;
-
-                // Listen to events
-                let events = ctx.input(|input| input.events.clone());
-
-                for event in events {
-                    if let egui::Event::Key {
-                        key,
-                        pressed,
-                        modifiers: _,
-                        repeat: _,
-                    } = event
-                    {
-                        let last_pressed = { *state.last_keys.entry(key).or_insert(false) };
-                        let value = if pressed { 1.0 } else { 0.0 };
-                        // true if the this key changed from last time
-                        // (was just pressed or released since the last frame)
-                        let key_changed = pressed != last_pressed;
-
-                        match key {
-                            Key::ArrowUp => input_y[0] = value,
-                            Key::ArrowDown => input_y[1] = value,
-                            Key::ArrowRight => input_x[0] = value,
-                            Key::ArrowLeft => input_x[1] = value,
-                            k => {
-                                use egui::Key::*;
-
-                                if key_changed && pressed {
-                                    pressed_key = key_to_char!(
-                                        k, Num1, '1', Num2, '2', Num3, '3', Num4, '4', Num5, '5',
-                                        Num6, '6', Num7, '7', Num8, '8', Num9, '9', A, 'A', B, 'B',
-                                        C, 'C', D, 'D', E, 'E', F, 'F', G, 'G', H, 'H', I, 'I', J,
-                                        'J', K, 'K', L, 'L', M, 'M', N, 'N', O, 'O', P, 'P', Q,
-                                        'Q', R, 'R', S, 'S', T, 'T', U, 'U', V, 'V', W, 'W', X,
-                                        'X', Y, 'Y', Z, 'Z'
-                                    );
-                                }
-                            }
-                        }
-
-                        state.last_keys.insert(key, pressed);
-                    }
-                }
+    use super::*;
+    use crate::{ball::BallAxis, model::Snapshot, tracking_widget::ConfigAppearance};
 
-                let input_axes = Vec2::new(input_x[0] - input_x[1], input_y[0] - input_y[1]);
+    fn app(input_mode: InputMode) -> (DialsApp, Receiver<InputEvent>) {
+        let snapshots = SnapshotCell::new(Snapshot {
+            dial_rows: Vec::new(),
+            ball_pos: Pos2::ZERO,
+            target_pos: None,
+            key_detected: false,
+            feedback_text: None,
+            outline_color: Color32::WHITE,
+            paused: None,
+            done: false,
+        });
+        let (inputs, received) = mpsc::channel();
+
+        let app = DialsApp::new(
+            &egui::Context::default(),
+            snapshots,
+            inputs,
+            input_mode,
+            ConfigKeyboard::default(),
+            ConfigMouse::default(),
+            TrackingView::new(BallAxis::default(), ConfigAppearance::default(), 0.1),
+        );
+
+        (app, received)
+    }
 
-                state.input_axes = input_axes;
-                state.input_x = input_x;
-                state.input_y = input_y;
-                state.pressed_key = pressed_key;
-            }
-            AppState::Done => {}
+    fn key(key: Key, pressed: bool) -> egui::Event {
+        egui::Event::Key {
+            key,
+            pressed,
+            repeat: false,
+            modifiers: Default::default(),
         }
+    }
 
-        // Ask for another repaint so that our app is continuously displayed
-        ctx.request_repaint();
+    #[test]
+    fn key_presses_are_timed_from_their_own_event() {
+        let (mut app, inputs) = app(InputMode::Joystick);
+        let first = Instant::now();
+        let second = first + Duration::from_millis(3);
+
+        // Both presses arrive before the next frame is drawn
+        app.on_events(&[key(Key::A, true)], first);
+        app.on_events(&[key(Key::B, true)], second);
+
+        let presses: Vec<_> = inputs
+            .try_iter()
+            .map(|event| match event {
+                InputEvent::Response(press) => (press.input, press.time),
+                event => panic!("{event:?} isn't a key press"),
+            })
+            .collect();
+
+        assert_eq!(
+            presses,
+            [
+                (ResponseInput::Key(Key::A), first),
+                (ResponseInput::Key(Key::B), second)
+            ]
+        );
+    }
+
+    #[test]
+    fn held_keys_only_respond_once() {
+        let (mut app, inputs) = app(InputMode::Joystick);
+        let now = Instant::now();
+
+        // Holding a key down repeats its press
+        app.on_events(&[key(Key::A, true)], now);
+        app.on_events(&[key(Key::A, true), key(Key::A, true)], now);
+        app.on_events(&[key(Key::A, false)], now);
+
+        let events: Vec<_> = inputs.try_iter().collect();
+
+        assert!(
+            matches!(
+                events[..],
+                [
+                    InputEvent::Response(_),
+                    InputEvent::ResponseReleased(ResponseInput::Key(Key::A))
+                ]
+            ),
+            "{events:?}"
+        );
+    }
+
+    #[test]
+    fn tracking_keys_move_the_ball_as_soon_as_they_change() {
+        let (mut app, inputs) = app(InputMode::Keyboard);
+        let now = Instant::now();
+
+        let mut axes_after = |events: &[egui::Event]| {
+            app.on_events(events, now);
+
+            match inputs.try_iter().collect::<Vec<_>>()[..] {
+                [InputEvent::KeyboardAxes(axes)] => axes,
+                ref events => panic!("{events:?} aren't the new axes"),
+            }
+        };
+
+        assert_eq!(axes_after(&[key(Key::ArrowUp, true)]), Vec2::new(0.0, 1.0));
+        assert_eq!(
+            axes_after(&[key(Key::ArrowLeft, true)]),
+            Vec2::new(-1.0, 1.0)
+        );
+        assert_eq!(
+            axes_after(&[key(Key::ArrowUp, false), key(Key::ArrowRight, true)]),
+            Vec2::new(0.0, 0.0)
+        );
     }
 }
@@ -1,34 +1,28 @@
-use std::f32;
-
 use eframe::{egui, emath::Vec2};
-use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-/// Area percentage rather than pixels
-const BALL_RADIUS: f32 = 0.03;
+use crate::{
+    config::ConfigBall,
+    dynamics::{ControlDynamics, Disturbance},
+    tracking::{ConfigTracking, TrackingMode},
+};
 
 /// Starting position which is the center of the screen.
 const BALL_START_POS: Vec2 = Vec2::new(0.0, 0.0);
 
-/// Parameter for the slow velocity, it cannot be changed within the program.
+/// Parameter for the slow velocity preset, other velocities can be given as numbers in the config.
 const BALL_SLOW_VELOCITY: f32 = 0.30;
 
-/// Parameter for the medium velocity, it cannot be changed within the program.
+/// Parameter for the medium velocity preset.
 const BALL_MEDIUM_VELOCITY: f32 = 0.60;
 
-/// Parameter for the fast velocity, it cannot be changed within the program.
+/// Parameter for the fast velocity preset.
 const BALL_FAST_VELOCITY: f32 = 1.20;
 
-const BALL_NUDGE_RATE: f32 = 1.2;
-
-/// Angle in radians around the crosshair to avoid randomly moving in
-const CROSSHAIR_AVOIDANCE_DEADZONE: f32 = f32::consts::FRAC_PI_2;
-
-/// Three types of velocity for the ball
+/// Three preset velocities for the ball, or an exact velocity
 /// By default the one that will be used the most is Slow as is the one that makes it easy to
 /// handle.
-/// The reason for this type is to have more freedom on the velocity for future use.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum BallVelocity {
     /// Slowest velocity and default by use [`BALL_SLOW_VELOCITY`]
@@ -40,6 +34,9 @@ pub enum BallVelocity {
     /// Fast velocity [`BALL_FAST_VELOCITY`]
     #[serde(rename = "fast")]
     Fast,
+    /// A velocity in units per second, where 1.0 unit is half the width of the tracking frame
+    #[serde(untagged)]
+    Custom(f32),
 }
 
 impl From<BallVelocity> for f32 {
@@ -48,10 +45,41 @@ impl From<BallVelocity> for f32 {
             BallVelocity::Slow => BALL_SLOW_VELOCITY,
             BallVelocity::Medium => BALL_MEDIUM_VELOCITY,
             BallVelocity::Fast => BALL_FAST_VELOCITY,
+            BallVelocity::Custom(velocity) => velocity,
         }
     }
 }
 
+/// Which directions the ball can move in
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BallAxis {
+    /// The ball moves anywhere in the frame
+    #[default]
+    Both,
+    /// The ball only moves left and right, through the crosshair
+    Horizontal,
+    /// The ball only moves up and down, through the crosshair
+    Vertical,
+}
+
+impl BallAxis {
+    /// The direction of the single axis the ball moves along, or [`None`] if it moves along both
+    pub fn direction(self) -> Option<Vec2> {
+        match self {
+            BallAxis::Both => None,
+            BallAxis::Horizontal => Some(Vec2::X),
+            BallAxis::Vertical => Some(Vec2::Y),
+        }
+    }
+
+    /// Removes any movement along the axis the ball can't move in
+    pub fn constrain(self, movement: Vec2) -> Vec2 {
+        self.direction()
+            .map_or(movement, |direction| direction * movement.dot(direction))
+    }
+}
+
 pub struct Ball {
     /// Current position in the screen.
     ///
@@ -59,50 +87,54 @@ pub struct Ball {
     /// This is later then factored to the monitor's dimension to be scale it properly.
     pos: Vec2,
 
-    velocity: Vec2,
+    /// Where the ball should be kept, which is the crosshair in the center of the frame unless
+    /// the tracking mode is [`TrackingMode::Pursuit`]
+    target: Vec2,
 
-    /// Keeps track of how long has the ball been running since last velocity change
-    time_running: f32,
+    /// [`TrackingMode`]
+    mode: TrackingMode,
 
-    /// Given by the config file, there will be a random time where the ball is suppose to change
-    /// it's velocity. This could be any times of seconds given by a range.
-    ///
-    /// For example any time from 1 second to 7 seconds.
-    velocity_change_time_at: f32,
+    /// [`BallAxis`]
+    axis: BallAxis,
 
-    /// The minimum value of the time range
-    pub random_direction_change_time_min: f32,
+    /// How close the ball's center can get to the edges of the frame, which is the radius it is
+    /// drawn with
+    radius: f32,
 
-    /// The maximum value of the time range
-    pub random_direction_change_time_max: f32,
+    /// How the participant's input moves the ball
+    control: Box<dyn ControlDynamics>,
+
+    /// What moves the ball away from the crosshair, or the target in pursuit tracking
+    disturbance: Box<dyn Disturbance>,
 }
 
 impl Ball {
-    /// Creates a new ball that begins in the default starting position with the ball's correct
-    /// starting velocity
-    pub fn new(
-        random_direction_change_time_min: f32,
-        random_direction_change_time_max: f32,
-        ball_velocity: BallVelocity,
-    ) -> Self {
-        let length: f32 = ball_velocity.into();
-
-        let mut rng = rand::thread_rng();
-
-        let radians = rng.gen_range(0.0..f32::consts::TAU);
-        let (x, y) = (radians.cos(), radians.sin());
-        let initial_vel = Vec2::new(x * length, y * length);
-
+    /// Creates a new ball that begins in the default starting position, with the dynamics and
+    /// disturbance from the config
+    pub fn new(config: &ConfigBall, tracking: &ConfigTracking) -> Self {
         Self {
             pos: BALL_START_POS,
-            velocity: initial_vel,
-            time_running: 0.0,
-            velocity_change_time_at: 0.0,
-            random_direction_change_time_min,
-            random_direction_change_time_max,
+            target: BALL_START_POS,
+            mode: tracking.mode,
+            axis: config.axis,
+            radius: tracking.appearance.ball_radius,
+            control: config.control_order.dynamics(config.control_gain),
+            disturbance: config.disturbance.disturbance(
+                config.ball_velocity,
+                config.random_direction_change_time_min,
+                config.random_direction_change_time_max,
+                config.axis,
+            ),
         }
     }
 
+    /// Makes the ball drift `speed_scale` times as fast as its configured velocity, and change
+    /// direction `direction_change_scale` times as often
+    pub fn set_difficulty(&mut self, speed_scale: f32, direction_change_scale: f32) {
+        self.disturbance
+            .set_difficulty(speed_scale, direction_change_scale);
+    }
+
     /// Movement of ball through the 2D plane
     ///
     /// The coordinate system used is (-1.0, -1.0) to (1.0, 1.0)
@@ -115,83 +147,64 @@ impl Ball {
     /// The center of the screen would be the (screen_width / 2, screen_height / 2) this can be
     /// translated to (0.0, 0.0).
     pub fn update(&mut self, input_axes: Vec2, delta_time: f32) {
-        let mut rng = rand::thread_rng();
-
-        if self.time_running >= self.velocity_change_time_at {
-            self.velocity = self.new_vel();
-
-            self.time_running = 0.0;
-            self.velocity_change_time_at = rng.gen_range(
-                self.random_direction_change_time_min..=self.random_direction_change_time_max,
-            );
+        match self.mode {
+            TrackingMode::Compensatory => {
+                self.pos += self
+                    .axis
+                    .constrain(self.disturbance.step(self.pos, delta_time));
+            }
+            TrackingMode::Pursuit => {
+                self.target += self
+                    .axis
+                    .constrain(self.disturbance.step(self.target, delta_time));
+
+                let disturbance = &mut self.disturbance;
+                keep_in_bounds(&mut self.target, self.radius, |normal| {
+                    disturbance.hit_wall(normal)
+                });
+            }
         }
 
-        self.pos.x += self.velocity.x * delta_time;
-        self.pos.y += self.velocity.y * delta_time;
+        // Based on input, correcting for the fact that positive y here is down
+        let input = self.axis.constrain(Vec2::new(input_axes.x, -input_axes.y));
+        self.pos += self.control.step(input, delta_time);
 
-        // Based on input
-        self.pos.x += input_axes.x * BALL_NUDGE_RATE * delta_time;
-        // Corrects for the fact that positive y here is down
-        self.pos.y -= input_axes.y * BALL_NUDGE_RATE * delta_time;
+        self.keep_in_bounds();
+    }
 
-        // This is for bounds checking on the ball
-        // The addition or subtraction inside the logic is so the circle does not use the center as
-        // the x or y location. This way the circle would not go through some of the borders.
+    /// Moves the ball straight to `pos`, in the same coordinate system as the ball's position
+    pub fn place(&mut self, pos: Vec2) {
+        self.pos = self.axis.constrain(pos);
 
-        if (self.pos.x + BALL_RADIUS) >= 1.0 {
-            self.pos.x = 1.0 - BALL_RADIUS;
-            self.velocity.x = -self.velocity.x.abs();
-        }
-        if (self.pos.x - BALL_RADIUS) <= -1.0 {
-            self.pos.x = -1.0 + BALL_RADIUS;
-            self.velocity.x = self.velocity.x.abs();
-        }
+        self.keep_in_bounds();
+    }
 
-        if (self.pos.y - BALL_RADIUS) <= -1.0 {
-            self.pos.y = -1.0 + BALL_RADIUS;
-            self.velocity.y = self.velocity.y.abs();
-        }
-        if (self.pos.y + BALL_RADIUS) >= 1.0 {
-            self.pos.y = 1.0 - BALL_RADIUS;
-            self.velocity.y = -self.velocity.y.abs();
-        }
+    /// Keeps the ball inside of the frame, and tells the dynamics about any walls it hit
+    fn keep_in_bounds(&mut self) {
+        let control = &mut self.control;
+        let disturbance = &mut self.disturbance;
+        let mode = self.mode;
+
+        keep_in_bounds(&mut self.pos, self.radius, |normal| {
+            control.hit_wall(normal);
 
-        self.time_running += delta_time;
+            // The disturbance only moves the ball in compensatory tracking
+            if mode == TrackingMode::Compensatory {
+                disturbance.hit_wall(normal);
+            }
+        });
     }
 
-    /// Function to make calculate the new velocity.
-    /// This uses geometry where it can choose any velocity within a 360 degree angle of the ball.
-    ///
-    /// Note however that this takes into account the position of the ball relative to the crosshair.
-    /// The ball will never randomly change direction in a way that will bring it towards the crosshair.
-    /// This is done by limiting the random ball with a 90 degree deadzone for possible new angles.
-    fn new_vel(&self) -> Vec2 {
-        let mut rng = rand::thread_rng();
-
-        // The crosshair is positioned at (0, 0) in our coordinate system
-        // We add pi to get the angle from the ball to the crosshair, rather than the
-        // angle from the crosshair to the ball
-        let crosshair_angle = (self.pos.angle() + f32::consts::PI) % f32::consts::TAU;
-        let left_deadzone = ((crosshair_angle - CROSSHAIR_AVOIDANCE_DEADZONE / 2.0)
-            + f32::consts::TAU)
-            % f32::consts::TAU;
-        let right_deadzone = ((crosshair_angle + CROSSHAIR_AVOIDANCE_DEADZONE / 2.0)
-            + f32::consts::TAU)
-            % f32::consts::TAU;
-
-        let absolute_smallest_difference =
-            f32::consts::PI - ((left_deadzone - right_deadzone).abs() - f32::consts::PI).abs();
-        let absolute_largest_difference = f32::consts::TAU - absolute_smallest_difference;
-        let radians_offset = rng.gen_range(0.0..absolute_largest_difference);
-        let radians = right_deadzone + radians_offset;
-
-        let (x, y) = (radians.cos(), radians.sin());
-
-        Vec2::new(x * self.velocity.length(), y * self.velocity.length())
+    /// The ball's offset from the crosshair in the center of the frame, or from the target in
+    /// pursuit tracking
+    pub fn error(&self) -> Vec2 {
+        self.pos - self.target
     }
 
-    pub fn current_rms_error(&self) -> f32 {
-        self.pos.x.powf(2.0) + self.pos.y.powf(2.0) // Distance from the center squared
+    /// Where the target is drawn, which is only set in pursuit tracking as the crosshair is
+    /// drawn otherwise
+    pub fn target_pos(&self) -> Option<egui::Pos2> {
+        (self.mode == TrackingMode::Pursuit).then(|| self.target.to_pos2())
     }
 
     pub fn pos(&self) -> egui::Pos2 {
@@ -199,8 +212,28 @@ impl Ball {
     }
 }
 
-impl Default for Ball {
-    fn default() -> Self {
-        Self::new(0.0, 0.0, BallVelocity::Slow)
+/// Keeps a ball of `radius` inside of the frame, calling `hit_wall` for each wall it hit with a
+/// normal that points from the wall into the frame
+fn keep_in_bounds(pos: &mut Vec2, radius: f32, mut hit_wall: impl FnMut(Vec2)) {
+    // This is for bounds checking on the ball
+    // The addition or subtraction inside the logic is so the circle does not use the center as
+    // the x or y location. This way the circle would not go through some of the borders.
+
+    if (pos.x + radius) >= 1.0 {
+        pos.x = 1.0 - radius;
+        hit_wall(Vec2::new(-1.0, 0.0));
+    }
+    if (pos.x - radius) <= -1.0 {
+        pos.x = -1.0 + radius;
+        hit_wall(Vec2::new(1.0, 0.0));
+    }
+
+    if (pos.y - radius) <= -1.0 {
+        pos.y = -1.0 + radius;
+        hit_wall(Vec2::new(0.0, 1.0));
+    }
+    if (pos.y + radius) >= 1.0 {
+        pos.y = 1.0 - radius;
+        hit_wall(Vec2::new(0.0, -1.0));
     }
 }
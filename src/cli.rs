@@ -0,0 +1,177 @@
+use anyhow::{bail, Result};
+
+use crate::DEFAULT_CONFIG_PATH;
+
+/// The usage text printed for `help` or when the arguments cannot be understood
+pub const USAGE: &str = "\
+Usage:
+    dials-research [run] [--config <path>] [--default-config]
+    dials-research init [<path>] [--force]
+    dials-research help
+
+Commands:
+    run     Runs a session using the configuration file (default)
+    init    Writes a commented configuration template to <path> (default: ./config.toml)
+    help    Prints this message
+
+Options:
+    --config <path>     The configuration file to use (default: ./config.toml)
+    --default-config    Runs the built-in demo session instead of reading a configuration file
+    --force             Allows `init` to overwrite an existing file";
+
+/// A command given to the program on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Runs a session
+    Run {
+        /// The path to the configuration file to read
+        config_path: String,
+        /// If the built-in demo configuration should be used instead of reading `config_path`
+        use_default_config: bool,
+    },
+    /// Writes a commented configuration template
+    Init {
+        /// Where the template should be written
+        path: String,
+        /// If an existing file at `path` may be overwritten
+        force: bool,
+    },
+    /// Prints the usage text
+    Help,
+}
+
+impl Command {
+    /// Parses the command from the program arguments, not including the program name
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut args = args.into_iter().peekable();
+
+        let command = match args.peek().map(|s| s.as_str()) {
+            Some("init") => {
+                args.next();
+                "init"
+            }
+            Some("help") | Some("--help") | Some("-h") => return Ok(Command::Help),
+            Some("run") => {
+                args.next();
+                "run"
+            }
+            _ => "run",
+        };
+
+        let mut config_path = None;
+        let mut use_default_config = false;
+        let mut force = false;
+
+        while let Some(arg) = args.next() {
+            match (command, arg.as_str()) {
+                ("run", "--config") => match args.next() {
+                    Some(path) => config_path = Some(path),
+                    None => bail!("`--config` requires a path\n\n{USAGE}"),
+                },
+                ("run", "--default-config") => use_default_config = true,
+                ("init", "--force") => force = true,
+                ("init", path) if !path.starts_with('-') && config_path.is_none() => {
+                    config_path = Some(path.to_owned())
+                }
+                (_, "--help") | (_, "-h") => return Ok(Command::Help),
+                (_, other) => bail!("Unexpected argument `{other}`\n\n{USAGE}"),
+            }
+        }
+
+        let config_path = config_path.unwrap_or_else(|| String::from(DEFAULT_CONFIG_PATH));
+
+        Ok(if command == "init" {
+            Command::Init {
+                path: config_path,
+                force,
+            }
+        } else {
+            Command::Run {
+                config_path,
+                use_default_config,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command> {
+        Command::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn run_is_the_default_command() {
+        let default_run = Command::Run {
+            config_path: String::from(DEFAULT_CONFIG_PATH),
+            use_default_config: false,
+        };
+
+        assert_eq!(parse(&[]).unwrap(), default_run);
+        assert_eq!(parse(&["run"]).unwrap(), default_run);
+    }
+
+    #[test]
+    fn run_reads_its_options() {
+        assert_eq!(
+            parse(&["--config", "a.yaml", "--default-config"]).unwrap(),
+            Command::Run {
+                config_path: String::from("a.yaml"),
+                use_default_config: true,
+            }
+        );
+        assert_eq!(
+            parse(&["run", "--config", "b.toml"]).unwrap(),
+            Command::Run {
+                config_path: String::from("b.toml"),
+                use_default_config: false,
+            }
+        );
+    }
+
+    #[test]
+    fn init_reads_its_path_and_force() {
+        assert_eq!(
+            parse(&["init"]).unwrap(),
+            Command::Init {
+                path: String::from(DEFAULT_CONFIG_PATH),
+                force: false,
+            }
+        );
+        assert_eq!(
+            parse(&["init", "new.toml", "--force"]).unwrap(),
+            Command::Init {
+                path: String::from("new.toml"),
+                force: true,
+            }
+        );
+    }
+
+    #[test]
+    fn help_can_be_asked_for_anywhere() {
+        for args in [
+            &["help"][..],
+            &["--help"],
+            &["-h"],
+            &["run", "--help"],
+            &["init", "-h"],
+        ] {
+            assert_eq!(parse(args).unwrap(), Command::Help, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn bad_arguments_are_errors() {
+        for args in [
+            &["--config"][..],
+            &["--force"],
+            &["init", "--default-config"],
+            &["init", "a.toml", "b.toml"],
+            &["run", "extra"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+    }
+}
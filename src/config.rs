@@ -1,11 +1,28 @@
-use crate::{ball::BallVelocity, dial::DialSpeed, tracking_widget::FeedbackColor};
+use crate::{
+    adaptive::ConfigAdaptive,
+    ball::{BallAxis, BallVelocity},
+    dial::{DialScript, DialSpeed, OutOfRange},
+    dial_motion::ConfigDialMotion,
+    dynamics::{ConfigDisturbance, ControlOrder},
+    joystick::ConfigJoystick,
+    keyboard::ConfigKeyboard,
+    metrics::ConfigMetrics,
+    response::ResponseInput,
+    timing::ConfigTiming,
+    tracking::ConfigTracking,
+    tracking_widget::FeedbackColor,
+};
 use serde::{Deserialize, Serialize};
 
+/// A commented configuration file that is written by the `init` command
+pub const CONFIG_TEMPLATE: &str = include_str!("config_template.toml");
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConfigTrial {
-    /// The key to respond to this experiment trial
-    /// A case insensitive character
-    pub correct_response_key: char,
+    /// The key or button to respond to this experiment trial
+    ///
+    /// If this is left out, the trial uses the [`ConfigDial::response_key`] of its dial
+    pub correct_response_key: Option<ResponseInput>,
 
     /// Text to display after a correct key was presseed
     pub feedback_text_correct: Option<String>,
@@ -29,7 +46,18 @@ pub struct ConfigTrial {
 
     /// The time at which the dial should drift outside of its range,
     /// and the alarm should sound
-    pub alarm_time: f32,
+    ///
+    /// Trials on a scripted dial leave this out, as their alarm sounds when the script leaves
+    /// the range
+    pub alarm_time: Option<f32>,
+
+    /// What the dial's needle does after it leaves the range, [`OutOfRange::Hold`] if this is
+    /// left out
+    pub out_of_range: Option<OutOfRange>,
+
+    /// The number of seconds after the alarm that the needle returns to the range on its own,
+    /// for [`OutOfRange::Recover`]
+    pub recover_after: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -45,6 +73,28 @@ pub struct ConfigDial {
 
     /// The relative movement speed of this dial
     pub speed: DialSpeed,
+
+    /// How this dial's needle moves while it is in range
+    #[serde(default)]
+    pub motion: ConfigDialMotion,
+
+    /// The key or button to respond to this dial's alarms with, for trials that don't specify
+    /// their own
+    pub response_key: Option<ResponseInput>,
+
+    /// A gamepad button that also responds to this dial's alarms, so that participants using the
+    /// joystick don't need to reach for the keyboard
+    pub response_button: Option<ResponseInput>,
+
+    /// Text that is shown underneath this dial, such as the name of its response key
+    pub label: Option<String>,
+
+    /// A CSV or JSON file with the exact path for this dial's needle, relative to the config file
+    pub script: Option<String>,
+
+    /// The path read from [`ConfigDial::script`] once the config file has been loaded
+    #[serde(skip)]
+    pub trajectory: Option<DialScript>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -76,9 +126,35 @@ pub struct ConfigBall {
     ///
     /// [`BallVelocity`]
     pub ball_velocity: BallVelocity,
+
+    /// Which derivative of the ball's position the input controls
+    ///
+    /// [`ControlOrder`]
+    #[serde(default)]
+    pub control_order: ControlOrder,
+
+    /// How strongly the input moves the ball, which defaults to
+    /// [`ControlOrder::default_gain`]
+    #[serde(default)]
+    pub control_gain: Option<f32>,
+
+    /// Which directions the ball can move in, for one-dimensional tracking
+    ///
+    /// [`BallAxis`]
+    #[serde(default)]
+    pub axis: BallAxis,
+
+    /// What moves the ball away from the crosshair
+    ///
+    /// [`ConfigDisturbance`]
+    #[serde(default)]
+    pub disturbance: ConfigDisturbance,
 }
 
-#[derive(Serialize, Deserialize)]
+/// The whole configuration of a session
+///
+/// This is built by [`crate::config_loader`] once every configuration file has been read and
+/// merged, so it isn't deserialized itself.
 pub struct Config {
     /// Where the output data gets stored to once the experiment is done
     pub output_data_path: Option<String>,
@@ -88,17 +164,38 @@ pub struct Config {
     /// [`InputMode`]
     pub input_mode: InputMode,
 
+    /// What kind of tracking task is run
+    pub tracking: ConfigTracking,
+
     /// Attributes necessary for the ball that we need
     ///
     /// ['ConfigBall']
     pub ball: ConfigBall,
 
+    /// Which keys move the ball, and how quickly, when the input mode is [`InputMode::Keyboard`]
+    pub keyboard: ConfigKeyboard,
+
+    /// How the mouse controls the ball when the input mode is [`InputMode::Mouse`]
+    pub mouse: ConfigMouse,
+
+    /// Which joystick to use and how it controls the ball when the input mode is
+    /// [`InputMode::Joystick`]
+    pub joystick: ConfigJoystick,
+
+    /// How often the model updates
+    pub timing: ConfigTiming,
+
+    /// How the tracking error metrics in the output are measured
+    pub metrics: ConfigMetrics,
+
+    /// How the ball's difficulty adapts to the participant's tracking
+    pub adaptive: ConfigAdaptive,
+
     /// The trials concerning dials and alarms that the program will execute and respond to
     ///
     /// [`ConfigTrial`]
     pub trials: Vec<ConfigTrial>,
 
-    #[serde(rename = "row")]
     /// Number of rows for dials along with Dial attributes needed.
     ///
     /// ['DialRow'] ['Dial'] for more information
@@ -117,7 +214,7 @@ pub struct ConfigDialRow {
     pub dials: Vec<ConfigDial>,
 }
 
-///Default Config file that is made if no "config.json" is detected
+/// The built-in demo session that is run with `--default-config`
 impl Default for Config {
     fn default() -> Self {
         let range_size = 4000.0;
@@ -126,19 +223,32 @@ impl Default for Config {
                 random_direction_change_time_min: 1.0,
                 random_direction_change_time_max: 8.0,
                 ball_velocity: BallVelocity::Slow,
+                control_order: ControlOrder::default(),
+                control_gain: None,
+                axis: BallAxis::default(),
+                disturbance: ConfigDisturbance::default(),
             },
             output_data_path: None,
+            tracking: ConfigTracking::default(),
             input_mode: InputMode::default(),
+            keyboard: ConfigKeyboard::default(),
+            mouse: ConfigMouse::default(),
+            joystick: ConfigJoystick::default(),
+            timing: ConfigTiming::default(),
+            metrics: ConfigMetrics::default(),
+            adaptive: ConfigAdaptive::default(),
             trials: (1u32..=6)
                 .map(|i| ConfigTrial {
-                    correct_response_key: char::from_digit(i, 10).unwrap(),
+                    correct_response_key: ResponseInput::digit(i),
                     feedback_text_correct: Some(String::from("CORRECT")),
                     feedback_text_incorrect: Some(String::from("INCORRECT")),
                     feedback_color_correct: Some(FeedbackColor::Green),
                     feedback_color_incorrect: Some(FeedbackColor::Red),
                     dial: format!("d{i}"),
                     alarm: format!("a{i}"),
-                    alarm_time: 4.0,
+                    alarm_time: Some(4.0),
+                    out_of_range: None,
+                    recover_after: None,
                 })
                 .collect(),
             dial_rows: vec![
@@ -149,6 +259,12 @@ impl Default for Config {
                             range_start: i as f32 * 200.0,
                             range_end: i as f32 * 200.0 + range_size,
                             speed: DialSpeed::Medium,
+                            motion: ConfigDialMotion::Sigmoid,
+                            response_key: None,
+                            response_button: None,
+                            label: None,
+                            script: None,
+                            trajectory: None,
                         })
                         .collect(),
                 },
@@ -159,6 +275,12 @@ impl Default for Config {
                             range_start: i as f32 * 200.0,
                             range_end: i as f32 * 200.0 + range_size,
                             speed: DialSpeed::Medium,
+                            motion: ConfigDialMotion::Sigmoid,
+                            response_key: None,
+                            response_button: None,
+                            label: None,
+                            script: None,
+                            trajectory: None,
                         })
                         .collect(),
                 },
@@ -179,7 +301,39 @@ impl Default for Config {
 pub enum InputMode {
     /// Joystick input through [`gilrs`]
     Joystick,
-    /// Keyboard input through WASD
+    /// Keyboard input, through the arrow keys unless other keys are set in [`ConfigKeyboard`]
     #[default]
     Keyboard,
+    /// Mouse input, by moving the pointer over the tracking frame
+    Mouse,
+}
+
+/// How the mouse pointer controls the ball in [`InputMode::Mouse`]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseControl {
+    /// The ball is wherever the pointer is over the tracking frame, and stays at the frame's edge
+    /// while the pointer is outside of it
+    Position,
+    /// The pointer's offset from the center of the tracking frame sets how fast the ball is pushed,
+    /// like a joystick
+    #[default]
+    Velocity,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+pub struct ConfigMouse {
+    /// How the pointer controls the ball
+    ///
+    /// [`MouseControl`]
+    #[serde(default)]
+    pub control: MouseControl,
+
+    /// If the pointer should be hidden while it is over the program's window
+    #[serde(default)]
+    pub hide_cursor: bool,
+
+    /// If the pointer should be kept inside the program's window during the session
+    #[serde(default)]
+    pub lock_pointer: bool,
 }
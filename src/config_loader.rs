@@ -0,0 +1,576 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    adaptive::ConfigAdaptive,
+    config::{Config, ConfigAlarm, ConfigBall, ConfigDialRow, ConfigMouse, ConfigTrial, InputMode},
+    dial::{DialSample, DialScript, PathSegment},
+    generator::ConfigGenerator,
+    joystick::ConfigJoystick,
+    keyboard::ConfigKeyboard,
+    metrics::ConfigMetrics,
+    timing::ConfigTiming,
+    tracking::ConfigTracking,
+};
+
+/// The file formats that a configuration file can be written in, chosen by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Picks the format from the extension of `path`. Files without an extension are read as TOML
+    fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            None => Ok(ConfigFormat::Toml),
+            Some(ext) => match ext.to_ascii_lowercase().as_str() {
+                "toml" => Ok(ConfigFormat::Toml),
+                "json" => Ok(ConfigFormat::Json),
+                "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+                _ => bail!(
+                    "`{}` has an unsupported extension `.{ext}`\nSupported extensions are: .toml, .json, .yaml, .yml",
+                    path.display()
+                ),
+            },
+        }
+    }
+
+    /// Parses `contents` as this format. The error messages of each parser contain the line and
+    /// column of the problem
+    fn parse(self, contents: &str) -> Result<ConfigFile, String> {
+        match self {
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// A single configuration file, which may only contain part of a [`Config`]
+///
+/// Files are merged together into a [`Config`] once all includes have been read, which is where
+/// the sections that may be left out are given their defaults.
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    /// Other configuration files that this file builds upon, relative to this file
+    #[serde(default)]
+    include: Vec<String>,
+
+    output_data_path: Option<String>,
+
+    input_mode: Option<InputMode>,
+
+    ball: Option<ConfigBall>,
+
+    keyboard: Option<ConfigKeyboard>,
+
+    mouse: Option<ConfigMouse>,
+
+    joystick: Option<ConfigJoystick>,
+
+    timing: Option<ConfigTiming>,
+
+    metrics: Option<ConfigMetrics>,
+
+    adaptive: Option<ConfigAdaptive>,
+
+    tracking: Option<ConfigTracking>,
+
+    #[serde(default)]
+    trials: Vec<ConfigTrial>,
+
+    /// A CSV file of trials, relative to this file, which are run after the trials in `trials`
+    trials_csv: Option<String>,
+
+    /// Generates trials which are run after all of the trials that are written out
+    generator: Option<ConfigGenerator>,
+
+    #[serde(default, rename = "row")]
+    dial_rows: Vec<ConfigDialRow>,
+
+    #[serde(default)]
+    alarms: Vec<ConfigAlarm>,
+}
+
+impl ConfigFile {
+    /// Layers `other` on top of this file. Settings in `other` replace the ones in this file, and
+    /// lists are appended after the ones in this file
+    fn merge(&mut self, other: ConfigFile) {
+        if other.output_data_path.is_some() {
+            self.output_data_path = other.output_data_path;
+        }
+        if other.input_mode.is_some() {
+            self.input_mode = other.input_mode;
+        }
+        if other.ball.is_some() {
+            self.ball = other.ball;
+        }
+        if other.keyboard.is_some() {
+            self.keyboard = other.keyboard;
+        }
+        if other.mouse.is_some() {
+            self.mouse = other.mouse;
+        }
+        if other.joystick.is_some() {
+            self.joystick = other.joystick;
+        }
+        if other.timing.is_some() {
+            self.timing = other.timing;
+        }
+        if other.metrics.is_some() {
+            self.metrics = other.metrics;
+        }
+        if other.adaptive.is_some() {
+            self.adaptive = other.adaptive;
+        }
+        if other.tracking.is_some() {
+            self.tracking = other.tracking;
+        }
+        if other.generator.is_some() {
+            self.generator = other.generator;
+        }
+
+        self.trials.extend(other.trials);
+        self.dial_rows.extend(other.dial_rows);
+        self.alarms.extend(other.alarms);
+    }
+}
+
+/// Parses the configuration file at `path` whose contents have already been read, along with every
+/// file that it includes
+///
+/// The format of each file is chosen by its extension: TOML, JSON or YAML. Included files are read
+/// first, in order, and the including file is layered on top of them. If a trial generator was
+/// given, its trials are generated once every file has been merged.
+pub fn parse(path: &Path, contents: &str) -> Result<Config> {
+    let mut sources = Vec::new();
+    let mut file = parse_file(
+        path,
+        contents,
+        &mut Vec::new(),
+        &mut HashMap::new(),
+        &mut sources,
+    )?;
+
+    if let Some(generator) = &file.generator {
+        let generated = generator
+            .generate(&file.dial_rows)
+            .context("Failed to generate trials")?;
+        file.trials.extend(generated);
+    }
+
+    let sources = sources
+        .iter()
+        .map(|p| format!("`{}`", p.display()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let missing = |section: &str| anyhow!("No `{section}` setting was found in {sources}");
+
+    Ok(Config {
+        output_data_path: file.output_data_path,
+        input_mode: file.input_mode.ok_or_else(|| missing("input_mode"))?,
+        ball: file.ball.ok_or_else(|| missing("ball"))?,
+        keyboard: file.keyboard.unwrap_or_default(),
+        mouse: file.mouse.unwrap_or_default(),
+        joystick: file.joystick.unwrap_or_default(),
+        timing: file.timing.unwrap_or_default(),
+        metrics: file.metrics.unwrap_or_default(),
+        adaptive: file.adaptive.unwrap_or_default(),
+        tracking: file.tracking.unwrap_or_default(),
+        trials: file.trials,
+        dial_rows: file.dial_rows,
+        alarms: file.alarms,
+    })
+}
+
+/// Parses a single file and recursively merges in its includes
+///
+/// `stack` holds the files that are currently being included, so that include cycles can be
+/// reported. `included_from` maps every file that has been included to the file that included it,
+/// so that a file included from two places isn't merged twice, and `sources` collects every file
+/// that was read.
+fn parse_file(
+    path: &Path,
+    contents: &str,
+    stack: &mut Vec<PathBuf>,
+    included_from: &mut HashMap<PathBuf, PathBuf>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<ConfigFile> {
+    let format = ConfigFormat::from_path(path)?;
+    let mut file = format
+        .parse(contents)
+        .map_err(|e| anyhow!("Failed to parse `{}`:\n{e}", path.display()))?;
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    if let Some(trials_csv) = file.trials_csv.take() {
+        file.trials
+            .extend(read_trials_csv(&directory.join(trials_csv), path)?);
+    }
+
+    for dial in file.dial_rows.iter_mut().flat_map(|row| &mut row.dials) {
+        if let Some(script) = &dial.script {
+            dial.trajectory = Some(read_dial_script(&directory.join(script), path)?);
+        }
+    }
+
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    stack.push(canonical);
+    sources.push(path.to_path_buf());
+
+    let mut merged = ConfigFile::default();
+
+    for include in std::mem::take(&mut file.include) {
+        let include_path = directory.join(&include);
+        let include_canonical = include_path
+            .canonicalize()
+            .unwrap_or_else(|_| include_path.clone());
+
+        if stack.contains(&include_canonical) {
+            bail!(
+                "`{}` includes `{include}`, which creates an include cycle",
+                path.display()
+            );
+        }
+
+        // Its trials, dials and alarms would be added twice
+        if let Some(first) = included_from.get(&include_canonical) {
+            bail!(
+                "`{}` is included by both `{}` and `{}`\nEach file can only be included once",
+                include_path.display(),
+                first.display(),
+                path.display()
+            );
+        }
+        included_from.insert(include_canonical, path.to_path_buf());
+
+        let include_contents = std::fs::read_to_string(&include_path).with_context(|| {
+            format!(
+                "Failed to read `{}`, included from `{}`",
+                include_path.display(),
+                path.display()
+            )
+        })?;
+
+        merged.merge(parse_file(
+            &include_path,
+            &include_contents,
+            stack,
+            included_from,
+            sources,
+        )?);
+    }
+
+    merged.merge(file);
+    stack.pop();
+
+    Ok(merged)
+}
+
+/// Reads a CSV file of trials referenced by the configuration file `referenced_from`
+///
+/// The first row holds the column names, which are the same as the names of the [`ConfigTrial`]
+/// settings. Optional settings may be left out entirely or left empty for individual trials.
+fn read_trials_csv(path: &Path, referenced_from: &Path) -> Result<Vec<ConfigTrial>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| {
+            format!(
+                "Failed to read trials CSV `{}`, referenced from `{}`",
+                path.display(),
+                referenced_from.display()
+            )
+        })?;
+
+    reader
+        .deserialize()
+        .map(|trial| {
+            trial.map_err(|e| anyhow!("Failed to parse trials CSV `{}`:\n{e}", path.display()))
+        })
+        .collect()
+}
+
+/// Reads a dial's scripted path referenced by the configuration file `referenced_from`
+///
+/// JSON files hold either `segments` or `samples`. CSV files hold segments if they have a
+/// `duration` column, with `start`, `end` and `duration` columns, and samples otherwise, with
+/// `time` and `value` columns.
+fn read_dial_script(path: &Path, referenced_from: &Path) -> Result<DialScript> {
+    let read_error = || {
+        format!(
+            "Failed to read dial script `{}`, referenced from `{}`",
+            path.display(),
+            referenced_from.display()
+        )
+    };
+    let parse_error = |e: &dyn std::fmt::Display| {
+        anyhow!("Failed to parse dial script `{}`:\n{e}", path.display())
+    };
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => {
+            let contents = std::fs::read_to_string(path).with_context(read_error)?;
+
+            serde_json::from_str(&contents).map_err(|e| parse_error(&e))
+        }
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_path(path)
+                .with_context(read_error)?;
+
+            let headers = reader.headers().map_err(|e| parse_error(&e))?;
+
+            if headers.iter().any(|header| header == "duration") {
+                reader
+                    .deserialize::<PathSegment>()
+                    .collect::<Result<_, _>>()
+                    .map(DialScript::Segments)
+                    .map_err(|e| parse_error(&e))
+            } else {
+                reader
+                    .deserialize::<DialSample>()
+                    .collect::<Result<_, _>>()
+                    .map(DialScript::Samples)
+                    .map_err(|e| parse_error(&e))
+            }
+        }
+        _ => bail!(
+            "Dial script `{}`, referenced from `{}`, must be a .csv or .json file",
+            path.display(),
+            referenced_from.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The settings that every config needs somewhere in its files
+    const REQUIRED: &str = r#"
+        input_mode = "keyboard"
+
+        [ball]
+        random_direction_change_time_min = 1.0
+        random_direction_change_time_max = 2.0
+        ball_velocity = "medium"
+    "#;
+
+    /// Writes `files` into a new directory for the test named `test`, and returns the directory
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("dials-research-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for (name, contents) in files {
+            std::fs::write(directory.join(name), contents).unwrap();
+        }
+
+        directory
+    }
+
+    /// Parses `main.toml` in `directory`
+    fn parse_main(directory: &Path) -> Result<Config> {
+        let path = directory.join("main.toml");
+        let contents = std::fs::read_to_string(&path).unwrap();
+
+        parse(&path, &contents)
+    }
+
+    #[test]
+    fn files_included_twice_are_an_error() {
+        let directory = write_files(
+            "diamond",
+            &[
+                ("main.toml", r#"include = ["b.toml", "c.toml"]"#),
+                ("b.toml", r#"include = ["d.toml"]"#),
+                ("c.toml", r#"include = ["d.toml"]"#),
+                ("d.toml", REQUIRED),
+            ],
+        );
+
+        let Err(error) = parse_main(&directory) else {
+            panic!("`d.toml` was merged twice");
+        };
+        let error = error.to_string();
+
+        assert!(error.contains("d.toml"), "{error}");
+        assert!(
+            error.contains("b.toml") && error.contains("c.toml"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn included_files_are_layered_underneath() {
+        let directory = write_files(
+            "layering",
+            &[
+                (
+                    "main.toml",
+                    r#"
+                        include = ["base.toml", "dials.yaml"]
+                        input_mode = "keyboard"
+
+                        [[trials]]
+                        dial = "d1"
+                        alarm = "a1"
+                        alarm_time = 3.0
+                        correct_response_key = "1"
+                        feedback_text_correct = "main"
+                    "#,
+                ),
+                (
+                    "base.toml",
+                    &format!(
+                        r#"
+                            {REQUIRED}
+
+                            [[trials]]
+                            dial = "d1"
+                            alarm = "a1"
+                            alarm_time = 1.0
+                            correct_response_key = "1"
+                            feedback_text_correct = "base"
+                        "#
+                    ),
+                ),
+                (
+                    "dials.yaml",
+                    "
+                        input_mode: joystick
+                        row:
+                          - dial:
+                              - name: d1
+                                range_start: 4000.0
+                                range_end: 6000.0
+                                speed: medium
+                        alarms:
+                          - name: a1
+                            audio_path: alarm.wav
+                        trials:
+                          - dial: d1
+                            alarm: a1
+                            alarm_time: 2.0
+                            correct_response_key: '1'
+                            feedback_text_correct: yaml
+                    ",
+                ),
+            ],
+        );
+
+        let config = parse_main(&directory).unwrap();
+
+        // The including file is read last, so its settings win over the YAML file's
+        assert!(matches!(config.input_mode, InputMode::Keyboard));
+        // Lists are joined in the order the files are read
+        let files: Vec<_> = config
+            .trials
+            .iter()
+            .map(|t| t.feedback_text_correct.as_deref())
+            .collect();
+        assert_eq!(files, [Some("base"), Some("yaml"), Some("main")]);
+        assert_eq!(config.dial_rows.len(), 1);
+        assert_eq!(config.alarms.len(), 1);
+    }
+
+    #[test]
+    fn include_cycles_are_an_error() {
+        let directory = write_files(
+            "cycle",
+            &[
+                ("main.toml", r#"include = ["a.toml"]"#),
+                ("a.toml", r#"include = ["b.toml"]"#),
+                ("b.toml", r#"include = ["main.toml"]"#),
+            ],
+        );
+
+        let Err(error) = parse_main(&directory) else {
+            panic!("the include cycle wasn't found");
+        };
+        let error = error.to_string();
+
+        assert!(
+            error.contains("b.toml") && error.contains("cycle"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn missing_settings_name_the_files_read() {
+        let directory = write_files(
+            "missing",
+            &[
+                ("main.toml", r#"include = ["a.toml"]"#),
+                ("a.toml", r#"input_mode = "keyboard""#),
+            ],
+        );
+
+        let Err(error) = parse_main(&directory) else {
+            panic!("the config had no ball");
+        };
+        let error = error.to_string();
+
+        assert!(
+            error.contains("`ball`") && error.contains("a.toml"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn trials_csv_columns_can_be_left_out_or_empty() {
+        let directory = write_files(
+            "trials-csv",
+            &[(
+                "trials.csv",
+                "dial,alarm,alarm_time,correct_response_key,feedback_text_correct
+                d1,a1,4.0,1,CORRECT
+                d2,a1,6.5,2,",
+            )],
+        );
+        let path = directory.join("trials.csv");
+
+        let trials = read_trials_csv(&path, &directory.join("main.toml")).unwrap();
+
+        assert_eq!(trials.len(), 2);
+        assert_eq!(trials[0].dial, "d1");
+        assert_eq!(trials[0].feedback_text_correct.as_deref(), Some("CORRECT"));
+        assert_eq!(trials[1].dial, "d2");
+        assert_eq!(trials[1].feedback_text_correct, None);
+        assert_eq!(trials[1].feedback_text_incorrect, None);
+    }
+
+    #[test]
+    fn trials_csv_errors_name_the_line() {
+        let directory = write_files(
+            "trials-csv-error",
+            &[(
+                "trials.csv",
+                "dial,alarm,alarm_time,correct_response_key
+                d1,a1,4.0,1
+                d2,a1,soon,2",
+            )],
+        );
+        let path = directory.join("trials.csv");
+
+        let Err(error) = read_trials_csv(&path, &directory.join("main.toml")) else {
+            panic!("the bad alarm time was read");
+        };
+        let error = error.to_string();
+
+        assert!(
+            error.contains("trials.csv") && error.contains("line: 3"),
+            "{error}"
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use derive_new::new;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+
+use crate::dial_motion::DialMotion;
 
 /// The value required to have the needle point to the very end of the dial
 pub const DIAL_MAX_VALUE: f32 = 10000.0;
@@ -13,13 +15,21 @@ const FAST_SECONDS_PER_SEGMENT: f32 = 1.0;
 const SLOW_SECONDS_PER_SEGMENT_DEVIATION: f32 = 1.0;
 const MEDIUM_SECONDS_PER_SEGMENT_DEVIATION: f32 = 0.5;
 const FAST_SECONDS_PER_SEGMENT_DEVIATION: f32 = 0.25;
-/// The number of path segments that should be generated after the dial is reset for the final time
-const AFTER_RESET_PATH_SEGMENTS: usize = 4000;
 /// The number of seconds to flash the dial needle for when an alarm is acknowledged
 const DIAL_FLASH_TIME: f32 = 0.5;
-
-/// Three types of speed for any specific dial
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+/// How far the sigmoid curve of a path segment is offset, so that it is centered on the middle of
+/// the segment
+const SIGMOID_X_OFFSET: f32 = -5.0;
+/// How fast the needle keeps moving away from the range for [`OutOfRange::Drift`], in values per
+/// second
+const DRIFT_AWAY_SPEED: f32 = 300.0;
+/// The number of seconds the needle takes to return to the middle of the range on its own
+const RECOVER_TIME: f32 = 1.0;
+/// How often a script is sampled when finding where it leaves a dial's range, in seconds
+const SCRIPT_EXIT_RESOLUTION: f32 = 0.001;
+
+/// How fast a dial's needle moves, either as one of three presets or as exact numbers
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum DialSpeed {
     /// Slowest velocity [`SLOW_SECONDS_PER_SEGMENT`]
@@ -31,6 +41,14 @@ pub enum DialSpeed {
     /// Fast velocity [`FAST_SECONDS_PER_SEGMENT`]
     #[serde(rename = "fast")]
     Fast,
+    /// The average number of seconds the needle takes to move between two points, and the random
+    /// deviation allowed in it
+    #[serde(untagged)]
+    Custom {
+        seconds_per_segment: f32,
+        #[serde(default)]
+        deviation: f32,
+    },
 }
 
 impl From<DialSpeed> for (f32, f32) {
@@ -42,10 +60,32 @@ impl From<DialSpeed> for (f32, f32) {
                 MEDIUM_SECONDS_PER_SEGMENT_DEVIATION,
             ),
             DialSpeed::Fast => (FAST_SECONDS_PER_SEGMENT, FAST_SECONDS_PER_SEGMENT_DEVIATION),
+            DialSpeed::Custom {
+                seconds_per_segment,
+                deviation,
+            } => (seconds_per_segment, deviation),
         }
     }
 }
 
+/// What happens to a dial's needle after it leaves the range in a trial
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutOfRange {
+    /// The needle stops just outside of the range until the alarm is responded to
+    #[default]
+    Hold,
+    /// The needle keeps moving away from the range until the alarm is responded to, or it
+    /// reaches the end of the dial
+    Drift,
+    /// The needle stops just outside of the range, and returns to it on its own if the alarm
+    /// isn't responded to in time, which counts as a miss
+    Recover,
+    /// The participant brings the needle back into the range by holding the response key, and
+    /// the trial ends once it is back
+    Correct,
+}
+
 /// A "range" which a dial can be inside or out of. This is used to keep track of if the dial is
 /// "in range" so that we know when to sound an alarm.
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, new)]
@@ -68,11 +108,11 @@ impl DialRange {
     }
 
     /// Returns a random value that is near the provided value within half of the maximum range
-    pub fn random_near(&self, value: f32) -> f32 {
+    pub fn random_near(&self, value: f32, rng: &mut impl Rng) -> f32 {
         // If we should increase or decrease
-        let decrease: bool = rand::random();
+        let decrease: bool = rng.gen();
 
-        let random_magnitude = (self.end - self.start) * rand::random::<f32>();
+        let random_magnitude = (self.end - self.start) * rng.gen::<f32>();
         let mut tamed_magnitude = random_magnitude / 2.0;
 
         if decrease {
@@ -87,16 +127,16 @@ impl DialRange {
     }
 
     /// Returns a random value that is inside of this range, with no other constraints
-    pub fn random_in(&self) -> f32 {
-        self.start + (self.end - self.start) * rand::random::<f32>()
+    pub fn random_in(&self, rng: &mut impl Rng) -> f32 {
+        self.start + (self.end - self.start) * rng.gen::<f32>()
     }
 
     /// Returns a value that is slightly outside of the range, useful for when we have to drift out
     /// but not too quickly. It takes into account the current value so that it can drift to the
     /// closer side
-    pub fn slightly_out(&self, value: f32) -> f32 {
+    pub fn slightly_out(&self, value: f32, rng: &mut impl Rng) -> f32 {
         let halfway = (self.end - self.start) / 2.0 + self.start;
-        let amount = rand::random::<f32>() * 400.0;
+        let amount = rng.gen_range(50.0..400.0);
 
         if value <= halfway {
             // Here we will choose a value that is less than our range
@@ -108,8 +148,179 @@ impl DialRange {
     }
 }
 
+/// A needle trajectory that is read from a file, so that every participant sees exactly the same
+/// needle movement
+///
+/// The trajectory starts when the session does, and the needle stays at the end of it once it is
+/// over.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum DialScript {
+    /// Path segments that are followed one after another, with the same smooth movement as the
+    /// random paths
+    Segments(Vec<PathSegment>),
+    /// The needle's value at points in time, with the needle moving in straight lines between them
+    Samples(Vec<DialSample>),
+}
+
+/// The value of a scripted dial's needle at a point in time
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DialSample {
+    /// Seconds since the session started
+    pub time: f32,
+    pub value: f32,
+}
+
+impl DialScript {
+    /// The path segments the needle follows
+    fn path(&self) -> VecDeque<PathSegment> {
+        match self {
+            DialScript::Segments(segments) => segments.iter().copied().collect(),
+            DialScript::Samples(samples) => {
+                let Some(first) = samples.first() else {
+                    return VecDeque::new();
+                };
+
+                // The needle waits at the first value until its time
+                let hold =
+                    PathSegment::new(first.value, first.value, first.time, SegmentShape::Linear);
+
+                std::iter::once(hold)
+                    .chain(samples.windows(2).map(|pair| {
+                        PathSegment::new(
+                            pair[0].value,
+                            pair[1].value,
+                            pair[1].time - pair[0].time,
+                            SegmentShape::Linear,
+                        )
+                    }))
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns a description of the first problem with this script, if there is one
+    pub fn check(&self) -> Result<(), String> {
+        let valid_value = |value: f32| (0.0..=DIAL_MAX_VALUE).contains(&value);
+
+        match self {
+            DialScript::Segments(segments) if segments.is_empty() => {
+                Err(String::from("It has no segments"))
+            }
+            DialScript::Samples(samples) if samples.is_empty() => {
+                Err(String::from("It has no samples"))
+            }
+            DialScript::Segments(segments) => {
+                for (i, segment) in segments.iter().enumerate() {
+                    if !(segment.duration.is_finite() && segment.duration > 0.0) {
+                        return Err(format!(
+                            "Segment #{} has a duration of {}, which must be more than 0.0",
+                            i + 1,
+                            segment.duration
+                        ));
+                    }
+
+                    if !(valid_value(segment.start) && valid_value(segment.end)) {
+                        return Err(format!(
+                            "Segment #{} goes outside of the dial, values must be from 0.0 to {DIAL_MAX_VALUE}",
+                            i + 1
+                        ));
+                    }
+                }
+
+                Ok(())
+            }
+            DialScript::Samples(samples) => {
+                let mut last_time = 0.0;
+
+                for (i, sample) in samples.iter().enumerate() {
+                    if !(sample.time.is_finite() && sample.time >= last_time) {
+                        return Err(format!(
+                            "Sample #{} is at {} seconds, sample times must be 0.0 or more and in order",
+                            i + 1,
+                            sample.time
+                        ));
+                    }
+
+                    if !valid_value(sample.value) {
+                        return Err(format!(
+                            "Sample #{} is outside of the dial, values must be from 0.0 to {DIAL_MAX_VALUE}",
+                            i + 1
+                        ));
+                    }
+
+                    last_time = sample.time;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// The times, in seconds since the session started, that the needle leaves `range`
+    pub fn range_exits(&self, range: &DialRange) -> Vec<f32> {
+        let mut exits = Vec::new();
+        let mut was_in_range = true;
+        let mut start_time = 0.0;
+
+        for segment in self.path() {
+            let steps = (segment.duration / SCRIPT_EXIT_RESOLUTION).ceil() as usize;
+
+            for step in 0..=steps {
+                let time = (step as f32 * SCRIPT_EXIT_RESOLUTION).min(segment.duration);
+                let in_range = range.contains(segment.value_at_time(time));
+
+                if was_in_range && !in_range {
+                    exits.push(start_time + time);
+                }
+                was_in_range = in_range;
+            }
+
+            start_time += segment.duration;
+        }
+
+        exits
+    }
+}
+
+/// When the alarms on scripted dials go off, in seconds since the session started
+///
+/// The alarm of a trial on a scripted dial is due the first time the dial's script leaves the
+/// range after the trial starts. The times are all worked out up front, so that an exit isn't
+/// missed by a frame. Exits from before the trial started are skipped, as the needle may be back
+/// in range by then.
+#[derive(Debug, Default)]
+pub struct ScriptedAlarms {
+    exits: HashMap<String, VecDeque<f32>>,
+}
+
+impl ScriptedAlarms {
+    /// Adds the alarms of the dial named `dial`, which follows `script` and has the in-range `range`
+    pub fn add(&mut self, dial: String, script: &DialScript, range: &DialRange) {
+        self.exits.insert(dial, script.range_exits(range).into());
+    }
+
+    /// When the alarm of the trial on `dial` that started at `trial_start` is due, if the dial is
+    /// scripted and leaves its range again
+    pub fn next(&mut self, dial: &str, trial_start: f32) -> Option<f32> {
+        let exits = self.exits.get_mut(dial)?;
+
+        while exits.front().is_some_and(|&exit| exit < trial_start) {
+            exits.pop_front();
+        }
+
+        exits.front().copied()
+    }
+
+    /// Moves on to the next alarm on `dial`, once the current one has gone off
+    pub fn sounded(&mut self, dial: &str) {
+        if let Some(exits) = self.exits.get_mut(dial) {
+            exits.pop_front();
+        }
+    }
+}
+
 /// Represents a dial inside of our application "model"
-#[derive(Debug, Clone)]
 pub struct Dial {
     // The current value of the dial, which is where the needle is pointing
     value: f32,
@@ -121,48 +332,131 @@ pub struct Dial {
     path: VecDeque<PathSegment>,
     // If this dial is randomly wandering in its range or if it is scheduled to drift out
     is_wandering: bool,
+    // If the needle is on its way back to the middle of the range after an alarm
+    is_recovering: bool,
     // The current time into the current path segment
     segment_time: f32,
     // The current direction of travel in the path segment.
     travel_direction: f32,
     // The remaining amount of time to flash the dial needle for if needed
     flash_time_remaining: Option<f32>,
-    // The movement speed of this dial
-    speed: DialSpeed,
+    // How this dial's random paths move
+    motion: Box<dyn DialMotion>,
+    // Text shown underneath the dial, such as its response key
+    label: Option<String>,
+    // If the path was read from a script, in which case it is never regenerated
+    is_scripted: bool,
 }
 
 impl Dial {
-    /// Creates a new Dial with the provided name and in-range
-    pub fn new(name: String, in_range: DialRange, speed: DialSpeed) -> Self {
+    /// Creates a new Dial with the provided name, in-range and optional label, which follows
+    /// `script` if it is given and otherwise moves randomly with `motion`
+    pub fn new(
+        name: String,
+        in_range: DialRange,
+        mut motion: Box<dyn DialMotion>,
+        label: Option<String>,
+        script: Option<&DialScript>,
+    ) -> Self {
+        let path = match script {
+            Some(script) => script.path(),
+            None => motion.wander(&in_range, in_range.middle()),
+        };
+        let value = path
+            .front()
+            .map_or(in_range.middle(), |segment| segment.start);
+
         Self {
-            value: in_range.middle(),
+            value,
             name,
             in_range,
-            path: generate_random_dial_path(&in_range, in_range.middle(), None, speed),
+            path,
             is_wandering: true,
+            is_recovering: false,
             segment_time: 0.0,
             travel_direction: 1.0,
             flash_time_remaining: None,
-            speed,
+            motion,
+            label,
+            is_scripted: script.is_some(),
         }
     }
 
     /// Resets the dial to the middle of the range and continues "wandering"
     /// If a drift out time is specified, that is used to generate the path, if not the dial will
     /// drift "forever"
+    ///
+    /// A scripted dial keeps following its script, and the drift out time is ignored.
     pub fn reset(&mut self, drift_out_time: Option<f32>) {
-        self.segment_time = 0.0;
         self.is_wandering = drift_out_time.is_none();
-        self.path = if self.is_wandering {
-            generate_random_dial_path(
-                &self.in_range,
-                self.in_range.middle(),
-                drift_out_time,
-                self.speed,
-            )
+        self.is_recovering = false;
+
+        if self.is_scripted {
+            return;
+        }
+
+        self.segment_time = 0.0;
+        self.path = match drift_out_time {
+            Some(drift_out_time) => {
+                self.motion
+                    .drift_out(&self.in_range, self.value, drift_out_time)
+            }
+            None => self.motion.wander(&self.in_range, self.in_range.middle()),
+        }
+    }
+
+    /// Makes the needle keep moving away from the range once it has drifted out, until it reaches
+    /// the end of the dial
+    pub fn keep_drifting(&mut self) {
+        if self.is_scripted {
+            return;
+        }
+
+        let last_value = self.path.back().map_or(self.value, |segment| segment.end);
+        let end = if last_value < self.in_range.start {
+            0.0
         } else {
-            generate_random_dial_path(&self.in_range, self.value, drift_out_time, self.speed)
+            DIAL_MAX_VALUE
+        };
+        let duration = (end - last_value).abs() / DRIFT_AWAY_SPEED;
+
+        self.path.push_back(PathSegment::new(
+            last_value,
+            end,
+            duration,
+            SegmentShape::Linear,
+        ));
+    }
+
+    /// Moves the needle up to `distance` towards the middle of the range, where it stays until it
+    /// is moved again. Returns true once the needle is back in range
+    pub fn correct(&mut self, distance: f32) -> bool {
+        if !self.is_scripted {
+            self.path.clear();
+            self.segment_time = 0.0;
+            self.value += (self.in_range.middle() - self.value).clamp(-distance, distance);
         }
+
+        self.in_range.contains(self.value)
+    }
+
+    /// Moves the needle smoothly back to the middle of the range, and continues "wandering"
+    pub fn recover(&mut self) {
+        self.is_wandering = true;
+
+        if self.is_scripted {
+            return;
+        }
+
+        self.is_recovering = true;
+
+        self.segment_time = 0.0;
+        self.path = VecDeque::from([PathSegment::new(
+            self.value,
+            self.in_range.middle(),
+            RECOVER_TIME,
+            SegmentShape::Sigmoid,
+        )]);
     }
 
     /// Updates the dial using the amount of time that has passed since the last update
@@ -172,16 +466,25 @@ impl Dial {
         // Update the current time within the segment
         self.segment_time += delta_time;
 
-        if let Some(current) = self.path.front() {
+        while let Some(current) = self.path.front() {
             // If we are still in our current path segment
             if current.in_segment(self.segment_time) {
                 // Calculate our current position in the path at the current time
                 self.value = current.value_at_time(self.segment_time);
-            } else {
-                // Move onto the next path segment
-                self.travel_direction = current.travel_direction();
-                self.path.pop_front();
-                self.segment_time = 0.0;
+                break;
+            }
+
+            // Move onto the next path segment, keeping the time that went past the end of this
+            // one so that the path stays on schedule
+            self.segment_time -= current.duration;
+            self.value = current.end;
+            self.travel_direction = current.travel_direction();
+            self.path.pop_front();
+
+            // A wandering dial keeps wandering once it reaches the end of its path
+            if self.path.is_empty() && self.is_wandering && !self.is_scripted {
+                self.path = self.motion.wander(&self.in_range, self.value);
+                self.is_recovering = false;
             }
         }
 
@@ -214,6 +517,16 @@ impl Dial {
         self.is_wandering
     }
 
+    // If the needle is still on its way back into the range, so it can't drift out yet
+    pub fn is_recovering(&self) -> bool {
+        self.is_recovering
+    }
+
+    // If the dial follows a script instead of moving randomly
+    pub fn is_scripted(&self) -> bool {
+        self.is_scripted
+    }
+
     // Tells the dial to begin flashing the needle
     pub fn flash(&mut self) {
         self.flash_time_remaining = Some(DIAL_FLASH_TIME);
@@ -223,28 +536,84 @@ impl Dial {
     pub fn is_flashing(&self) -> bool {
         self.flash_time_remaining.is_some()
     }
+
+    // The text shown underneath this dial, if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
 }
 
 /// A single segment in a Dial's random path that it traverses over time
-#[derive(Debug, Clone, Copy)]
-struct PathSegment {
+#[derive(new, Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct PathSegment {
     /// The start position of the dial
-    start: f32,
+    pub start: f32,
     /// The end position of the dial
-    end: f32,
+    pub end: f32,
     /// The time that this path segment should take
-    duration: f32,
+    pub duration: f32,
+    /// How the needle moves from the start to the end
+    #[serde(skip)]
+    pub shape: SegmentShape,
+}
+
+/// How the needle moves along a [`PathSegment`]
+#[derive(Debug, Default, Clone, Copy)]
+pub enum SegmentShape {
+    /// The needle speeds up and then slows down again, along a sigmoid curve
+    #[default]
+    Sigmoid,
+    /// The needle moves at a constant speed
+    Linear,
 }
 
 impl PathSegment {
     /// Returns the value at the time in the path segment.
     fn value_at_time(&self, time: f32) -> f32 {
-        const X_OFFSET: f32 = -5.0;
+        if self.duration <= 0.0 {
+            return self.end;
+        }
 
         let scale_factor = self.end - self.start;
-        let x_value = (10.0 / self.duration) * time;
+        let time = time.clamp(0.0, self.duration);
+
+        match self.shape {
+            SegmentShape::Sigmoid => {
+                // The curve is stretched to start exactly at the start and end exactly at the end
+                let low = sigmoid(SIGMOID_X_OFFSET);
+                let high = sigmoid(-SIGMOID_X_OFFSET);
+                let x_value = (10.0 / self.duration) * time;
 
-        sigmoid(x_value + X_OFFSET) * scale_factor + self.start
+                (sigmoid(x_value + SIGMOID_X_OFFSET) - low) / (high - low) * scale_factor
+                    + self.start
+            }
+            SegmentShape::Linear => (time / self.duration) * scale_factor + self.start,
+        }
+    }
+
+    /// Returns the time in the path segment at which the needle passes `value`, which is the
+    /// inverse of [`PathSegment::value_at_time`]. Values before the start or after the end are
+    /// passed at the start or the end of the segment
+    pub fn time_at_value(&self, value: f32) -> f32 {
+        let fraction = if self.start == self.end {
+            0.0
+        } else {
+            ((value - self.start) / (self.end - self.start)).clamp(0.0, 1.0)
+        };
+
+        match self.shape {
+            SegmentShape::Sigmoid => {
+                let low = sigmoid(SIGMOID_X_OFFSET);
+                let high = sigmoid(-SIGMOID_X_OFFSET);
+                let y_value = low + fraction * (high - low);
+
+                // The inverse of the sigmoid is the logit
+                let x_value = (y_value / (1.0 - y_value)).ln() - SIGMOID_X_OFFSET;
+
+                (x_value * self.duration / 10.0).clamp(0.0, self.duration)
+            }
+            SegmentShape::Linear => fraction * self.duration,
+        }
     }
 
     /// Returns true if the time is within the path segment duration, false if not
@@ -262,99 +631,290 @@ impl PathSegment {
     }
 }
 
-/// Generates a new random dial path for the dial to perform within the time_to_drift, and within
-/// the given range.
-fn generate_random_dial_path(
-    range: &DialRange,
-    start_value: f32,
-    drift_out_time: Option<f32>,
-    speed: DialSpeed,
-) -> VecDeque<PathSegment> {
-    let (seconds_per_segment, seconds_per_segment_deviation) = speed.into();
-    let min_segment_time: f32 = seconds_per_segment - seconds_per_segment_deviation;
-    let max_segment_time: f32 = seconds_per_segment + seconds_per_segment_deviation;
-
-    let mut segments = VecDeque::new();
-
-    if let Some(drift_out_time) = drift_out_time {
-        let mut time_remaining = drift_out_time;
-        let mut start = range.random_in();
-        let mut end = range.slightly_out(start);
-        let duration = rand::thread_rng().gen_range(min_segment_time..=max_segment_time);
-
-        let first_segment = PathSegment {
-            start,
-            end,
-            duration,
-        };
+fn sigmoid(a: f32) -> f32 {
+    1.0 / (1.0 + (-a).exp())
+}
 
-        let correct_duration_time = time_to_pass_end(range, duration, end);
-        time_remaining -= correct_duration_time;
-        end = start;
-        start = range.random_near(end);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dial_motion::ConfigDialMotion;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    const MOTIONS: [ConfigDialMotion; 4] = [
+        ConfigDialMotion::Sigmoid,
+        ConfigDialMotion::Linear,
+        ConfigDialMotion::OrnsteinUhlenbeck {
+            reversion: 0.5,
+            volatility: 0.4,
+        },
+        ConfigDialMotion::Sinusoidal {
+            period: 5.0,
+            amplitude: 1.0,
+        },
+    ];
+
+    const SPEEDS: [DialSpeed; 5] = [
+        DialSpeed::Slow,
+        DialSpeed::Medium,
+        DialSpeed::Fast,
+        DialSpeed::Custom {
+            seconds_per_segment: 0.4,
+            deviation: 0.0,
+        },
+        DialSpeed::Custom {
+            seconds_per_segment: 5.0,
+            deviation: 4.5,
+        },
+    ];
+
+    const RANGES: [DialRange; 3] = [
+        DialRange {
+            start: 1000.0,
+            end: 5000.0,
+        },
+        DialRange {
+            start: 4900.0,
+            end: 5100.0,
+        },
+        DialRange {
+            start: 0.0,
+            end: 9800.0,
+        },
+    ];
+
+    const DRIFT_OUT_TIMES: [f32; 6] = [0.0, 0.2, 1.0, 2.7, 6.0, 21.5];
+
+    /// The time at which the needle first leaves `range` along `path`, after coming back into it if
+    /// it starts outside, found without [`PathSegment::time_at_value`]
+    fn first_exit(range: &DialRange, path: &VecDeque<PathSegment>) -> Option<f32> {
+        let mut start_time = 0.0;
+        let mut path = path.iter().peekable();
+
+        // A needle that starts out of the range has to come back into it before it can leave
+        while let Some(segment) = path.next_if(|segment| !range.contains(segment.start)) {
+            start_time += segment.duration;
+        }
 
-        segments.push_back(first_segment);
+        for segment in path {
+            // Segments only ever move one way, so they are out of range at some point only if
+            // they end out of it
+            if !range.contains(segment.start) {
+                return Some(start_time);
+            }
 
-        while time_remaining > max_segment_time {
-            let duration = rand::thread_rng().gen_range(min_segment_time..=max_segment_time);
+            if !range.contains(segment.end) {
+                let (mut inside, mut outside) = (0.0, segment.duration);
 
-            let segment = PathSegment {
-                start,
-                end,
-                duration,
-            };
+                for _ in 0..60 {
+                    let middle = (inside + outside) / 2.0;
 
-            segments.push_front(segment);
+                    if range.contains(segment.value_at_time(middle)) {
+                        inside = middle;
+                    } else {
+                        outside = middle;
+                    }
+                }
 
-            let last_end = end;
-            end = start;
-            start = range.random_near(last_end);
-            time_remaining -= duration;
+                return Some(start_time + outside);
+            }
+
+            start_time += segment.duration;
         }
 
-        let final_segment = PathSegment {
-            start: start_value,
-            end,
-            duration: time_remaining,
-        };
+        None
+    }
 
-        segments.push_front(final_segment);
-    } else {
-        let mut last_value = start_value;
+    #[test]
+    fn drift_out_paths_leave_the_range_on_time() {
+        for seed in 0..20 {
+            for config in MOTIONS {
+                for speed in SPEEDS {
+                    let mut motion = config.motion(speed, seed);
+
+                    for range in RANGES {
+                        let below = (range.start - 300.0).max(0.0);
+                        let above = (range.end + 300.0).min(DIAL_MAX_VALUE);
+
+                        for start in [below, range.start, range.middle(), range.end, above] {
+                            for drift_out_time in DRIFT_OUT_TIMES {
+                                let path = motion.drift_out(&range, start, drift_out_time);
+                                let exit = first_exit(&range, &path);
+
+                                assert!(
+                                    exit.is_some_and(|exit| (exit - drift_out_time).abs() < 1e-3),
+                                    "{config:?} at {speed:?} with seed {seed} in {range:?} from {start} left at {exit:?} instead of {drift_out_time}",
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dials_leave_the_range_on_the_frame_of_the_alarm() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for seed in 0..10 {
+            for motion in MOTIONS {
+                for speed in SPEEDS {
+                    let range = RANGES[seed as usize % RANGES.len()];
+                    let mut dial =
+                        Dial::new(String::new(), range, motion.motion(speed, seed), None, None);
+
+                    // The dial wanders for a while first, with uneven frame times
+                    for _ in 0..200 {
+                        dial.update(rng.gen_range(0.005..0.03));
+                    }
+
+                    let drift_out_time = rng.gen_range(0.0..15.0);
+                    dial.reset(Some(drift_out_time));
+
+                    let mut time = 0.0;
+                    let mut frame_time = 0.0;
+
+                    while range.contains(dial.value()) {
+                        assert!(time < drift_out_time + 1.0, "the dial never left the range");
+
+                        frame_time = rng.gen_range(0.005..0.03);
+                        time += frame_time;
+                        dial.update(frame_time);
+                    }
+
+                    assert!(
+                        time >= drift_out_time - 1e-3 && time - frame_time < drift_out_time + 1e-3,
+                        "{motion:?} at {speed:?} with seed {seed} left on the frame ending at {time} instead of the one with {drift_out_time}",
+                    );
+                }
+            }
+        }
+    }
 
-        for _ in 0..AFTER_RESET_PATH_SEGMENTS {
-            let next_value = range.random_near(last_value);
-            let duration = rand::thread_rng().gen_range(min_segment_time..=max_segment_time);
+    #[test]
+    fn recovering_dials_get_back_in_range_before_drifting_out() {
+        const FRAME_TIME: f32 = 0.01;
 
-            let segment = PathSegment {
-                start: last_value,
-                end: next_value,
-                duration,
-            };
+        for config in MOTIONS {
+            let range = RANGES[0];
+            let motion = config.motion(DialSpeed::Fast, 0);
+            let mut dial = Dial::new(String::new(), range, motion, None, None);
 
-            segments.push_back(segment);
+            // The needle stays out once it has drifted out, until the trial ends as a miss
+            dial.reset(Some(0.5));
+            for _ in 0..200 {
+                dial.update(FRAME_TIME);
+            }
+            assert!(!range.contains(dial.value()));
 
-            last_value = next_value;
+            dial.recover();
+            let mut time = 0.0;
+
+            while dial.is_recovering() {
+                assert!(time < RECOVER_TIME + 0.1, "{config:?} never recovered");
+
+                dial.update(FRAME_TIME);
+                time += FRAME_TIME;
+            }
+
+            assert!(time >= RECOVER_TIME - 1e-3 && range.contains(dial.value()));
         }
     }
 
-    segments
-}
+    /// A script that moves in straight lines between `(time, value)` samples
+    fn samples(samples: &[(f32, f32)]) -> DialScript {
+        DialScript::Samples(
+            samples
+                .iter()
+                .map(|&(time, value)| DialSample { time, value })
+                .collect(),
+        )
+    }
 
-// Returns the time at which the dial needle will pass the end of the range and head towards point "end"
-// given the duration of the path segment
-fn time_to_pass_end(range: &DialRange, duration: f32, end: f32) -> f32 {
-    // This equation was derived on paper by taking our equation for needle position and solving for time
+    #[test]
+    fn scripted_alarms_skip_exits_from_before_the_trial() {
+        const TICK: f32 = 0.002;
+        const RESPONSE_TIME: f32 = 0.5;
+
+        let range = DialRange::new(4000.0, 6000.0);
+        // The second time `a` leaves is while its first alarm is still on
+        let a = samples(&[
+            (0.0, 5000.0),
+            (1.9, 5000.0),
+            (2.0, 7000.0),
+            (2.1, 5000.0),
+            (2.2, 5000.0),
+            (2.3, 7000.0),
+            (2.4, 5000.0),
+            (6.0, 5000.0),
+            (6.1, 7000.0),
+            (6.2, 5000.0),
+        ]);
+        // The first time `b` leaves is during a trial on `a`
+        let b = samples(&[
+            (0.0, 5000.0),
+            (1.0, 5000.0),
+            (1.1, 3000.0),
+            (1.2, 5000.0),
+            (4.0, 5000.0),
+            (4.1, 3000.0),
+            (4.2, 5000.0),
+        ]);
+
+        let names = ["a", "b"];
+        let scripts = [a, b];
+        let mut alarms = ScriptedAlarms::default();
+        let mut dials = [0, 1].map(|i| {
+            let motion = ConfigDialMotion::Sigmoid.motion(DialSpeed::Medium, 0);
+            alarms.add(String::from(names[i]), &scripts[i], &range);
+            Dial::new(
+                String::from(names[i]),
+                range,
+                motion,
+                None,
+                Some(&scripts[i]),
+            )
+        });
 
-    let (path_start, path_zero) = if end > range.start && end > range.end {
-        (range.start, range.end)
-    } else {
-        (range.end, range.start)
-    };
+        let exits = scripts.each_ref().map(|script| script.range_exits(&range));
+        assert_eq!(exits.each_ref().map(Vec::len), [3, 2]);
 
-    (duration / 10.0) * (((end - path_start) / (path_zero - path_start)).ln() + 5.0)
-}
+        let mut time = 0.0;
+        let mut trial_start = 0.0;
 
-fn sigmoid(a: f32) -> f32 {
-    1.0 / (1.0 + (-a).exp())
+        // The second trial on `a` skips the exit during its first alarm
+        for (dial, exit) in [(0, exits[0][0]), (1, exits[1][1]), (0, exits[0][2])] {
+            while !alarms
+                .next(names[dial], trial_start)
+                .is_some_and(|next| time >= next)
+            {
+                assert!(time < 10.0, "the alarm on `{}` never went off", names[dial]);
+
+                dials.iter_mut().for_each(|d| d.update(TICK));
+                time += TICK;
+            }
+            alarms.sounded(names[dial]);
+
+            // Exits from before the trial started don't sound the alarm, it waits for the next
+            assert!(
+                time >= exit && time < exit + TICK + 1e-3,
+                "the alarm on `{}` went off at {time} instead of {exit}",
+                names[dial]
+            );
+            assert!(!range.contains(dials[dial].value()));
+
+            // The participant responds a while later, which starts the next trial
+            let response_time = time + RESPONSE_TIME;
+            while time < response_time {
+                dials.iter_mut().for_each(|d| d.update(TICK));
+                time += TICK;
+            }
+            trial_start = time;
+        }
+
+        assert_eq!(alarms.next("a", trial_start), None);
+        assert_eq!(alarms.next("b", trial_start), None);
+    }
 }
@@ -0,0 +1,449 @@
+use std::{collections::VecDeque, f32::consts::TAU, ops::RangeInclusive};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::dial::{DialRange, DialSpeed, PathSegment, SegmentShape};
+
+/// The number of path segments that are generated at a time while a dial moves between random
+/// points
+const WANDER_PATH_SEGMENTS: usize = 4000;
+/// The number of seconds of a sampled motion that are generated at a time while a dial wanders
+const WANDER_SAMPLED_TIME: f32 = 120.0;
+/// The number of seconds between the points that a sampled motion is made of
+const SAMPLE_INTERVAL: f32 = 0.05;
+/// How far through a segment leaving the range the needle has to be when it passes the end of
+/// the range, or it leaves from the other side instead
+const EDGE_EXIT_FRACTION: f32 = 0.05;
+
+/// How a dial's needle moves while it is in range
+///
+/// Whichever motion is used, the needle still leaves the range at each trial's alarm time.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConfigDialMotion {
+    /// The needle moves between random points in the range, speeding up and then slowing down
+    /// again along a sigmoid curve
+    #[default]
+    Sigmoid,
+    /// The needle moves between random points in the range at a constant speed
+    Linear,
+    /// The needle jitters randomly, and is pulled back towards the middle of the range the further
+    /// it strays (an Ornstein-Uhlenbeck process)
+    OrnsteinUhlenbeck {
+        /// How strongly the needle is pulled back towards the middle, per second
+        reversion: f32,
+        /// How much the needle jitters, in half-widths of the range per square root second
+        volatility: f32,
+    },
+    /// The needle swings smoothly back and forth around the middle of the range
+    Sinusoidal {
+        /// The number of seconds for one full swing back and forth
+        period: f32,
+        /// How far the needle swings from the middle, where 1.0 is the edge of the range
+        amplitude: f32,
+    },
+}
+
+impl ConfigDialMotion {
+    /// Creates the motion, where `speed` sets how long each segment takes, and how long the
+    /// needle takes to leave the range
+    ///
+    /// The same seed always generates the same paths.
+    pub fn motion(self, speed: DialSpeed, seed: u64) -> Box<dyn DialMotion> {
+        let (seconds_per_segment, seconds_per_segment_deviation) = speed.into();
+        let segment_times = (seconds_per_segment - seconds_per_segment_deviation)
+            ..=(seconds_per_segment + seconds_per_segment_deviation);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        match self {
+            ConfigDialMotion::Sigmoid => Box::new(SegmentMotion {
+                segment_times,
+                shape: SegmentShape::Sigmoid,
+                rng,
+            }),
+            ConfigDialMotion::Linear => Box::new(SegmentMotion {
+                segment_times,
+                shape: SegmentShape::Linear,
+                rng,
+            }),
+            ConfigDialMotion::OrnsteinUhlenbeck {
+                reversion,
+                volatility,
+            } => Box::new(SampledMotion {
+                segment_times,
+                process: OrnsteinUhlenbeck {
+                    reversion,
+                    volatility,
+                },
+                rng,
+            }),
+            ConfigDialMotion::Sinusoidal { period, amplitude } => Box::new(SampledMotion {
+                segment_times,
+                process: Sinusoid {
+                    period,
+                    amplitude,
+                    phase: rng.gen_range(0.0..TAU),
+                },
+                rng,
+            }),
+        }
+    }
+}
+
+/// Generates the paths that a dial's needle follows
+pub trait DialMotion: Send {
+    /// A path from `start` that stays inside `range`, which the dial asks for more of once it
+    /// has been followed to the end
+    fn wander(&mut self, range: &DialRange, start: f32) -> VecDeque<PathSegment>;
+
+    /// A path from `start` that stays inside `range` until it leaves it exactly `drift_out_time`
+    /// seconds later
+    fn drift_out(
+        &mut self,
+        range: &DialRange,
+        start: f32,
+        drift_out_time: f32,
+    ) -> VecDeque<PathSegment>;
+}
+
+/// Moves between random points in the range, with each move taking a random time
+struct SegmentMotion {
+    segment_times: RangeInclusive<f32>,
+    shape: SegmentShape,
+    rng: ChaCha8Rng,
+}
+
+impl SegmentMotion {
+    fn segment(&mut self, start: f32, end: f32) -> PathSegment {
+        let duration = self.rng.gen_range(self.segment_times.clone());
+
+        PathSegment::new(start, end, duration, self.shape)
+    }
+}
+
+impl DialMotion for SegmentMotion {
+    fn wander(&mut self, range: &DialRange, start: f32) -> VecDeque<PathSegment> {
+        let mut segments = VecDeque::new();
+        let mut last_value = start;
+
+        for _ in 0..WANDER_PATH_SEGMENTS {
+            let next_value = range.random_near(last_value, &mut self.rng);
+
+            segments.push_back(self.segment(last_value, next_value));
+
+            last_value = next_value;
+        }
+
+        segments
+    }
+
+    fn drift_out(
+        &mut self,
+        range: &DialRange,
+        start_value: f32,
+        drift_out_time: f32,
+    ) -> VecDeque<PathSegment> {
+        // A needle that is out of the range comes back in first, so that it can leave on time
+        if !range.contains(start_value) {
+            let back = return_segment(
+                range,
+                start_value,
+                drift_out_time,
+                &self.segment_times,
+                self.shape,
+                &mut self.rng,
+            );
+            let mut segments = self.drift_out(range, back.end, drift_out_time - back.duration);
+            segments.push_front(back);
+
+            return segments;
+        }
+
+        // The last segment takes the needle out of the range, from a random point inside it
+        let exit_start = range.random_in(&mut self.rng);
+        let exit_end = range.slightly_out(exit_start, &mut self.rng);
+        let exit = self.segment(exit_start, exit_end);
+        let lead_time = drift_out_time - time_to_pass_end(range, &exit);
+
+        // Without the time to get to that point first, the needle leaves from where it is
+        if lead_time < *self.segment_times.start() {
+            let segment = exit_segment(
+                range,
+                start_value,
+                drift_out_time,
+                self.shape,
+                &mut self.rng,
+            );
+
+            return VecDeque::from([segment]);
+        }
+
+        // The points leading up to the exit are picked backwards from it, and their segments are
+        // scaled to take exactly the lead time between them
+        let average_time = (self.segment_times.start() + self.segment_times.end()) / 2.0;
+        let count = ((lead_time / average_time).round() as usize).max(1);
+
+        let mut points = vec![exit_start];
+        while points.len() < count {
+            let next = range.random_near(*points.last().unwrap(), &mut self.rng);
+            points.push(next);
+        }
+        points.push(start_value);
+
+        let durations: Vec<f32> = (0..count)
+            .map(|_| self.rng.gen_range(self.segment_times.clone()))
+            .collect();
+        let scale = lead_time / durations.iter().sum::<f32>();
+
+        let mut segments: VecDeque<_> = points
+            .windows(2)
+            .rev()
+            .zip(durations)
+            .map(|(pair, duration)| {
+                PathSegment::new(pair[1], pair[0], duration * scale, self.shape)
+            })
+            .collect();
+
+        segments.push_back(exit);
+
+        segments
+    }
+}
+
+/// A random or periodic process that the needle follows, which is sampled into short straight
+/// segments
+trait Process: Send {
+    /// The needle's value `delta_time` seconds after it was at `value`
+    fn next_value(
+        &mut self,
+        range: &DialRange,
+        value: f32,
+        delta_time: f32,
+        rng: &mut ChaCha8Rng,
+    ) -> f32;
+
+    /// Picks up the process from `value`, after the needle was moved by something else
+    fn resume(&mut self, _range: &DialRange, _value: f32) {}
+}
+
+/// Follows a [`Process`] while the needle is in range, and leaves the range in a straight line
+struct SampledMotion<P: Process> {
+    /// The times the needle can take to leave the range
+    segment_times: RangeInclusive<f32>,
+    process: P,
+    rng: ChaCha8Rng,
+}
+
+impl<P: Process> SampledMotion<P> {
+    /// The process sampled for `duration` seconds from `start`
+    fn sample(&mut self, range: &DialRange, start: f32, duration: f32) -> VecDeque<PathSegment> {
+        self.process.resume(range, start);
+
+        let mut segments = VecDeque::new();
+        let mut value = start;
+        let mut time = 0.0;
+
+        while time < duration {
+            let delta_time = SAMPLE_INTERVAL.min(duration - time);
+            let next_value = self
+                .process
+                .next_value(range, value, delta_time, &mut self.rng);
+
+            segments.push_back(PathSegment::new(
+                value,
+                next_value,
+                delta_time,
+                SegmentShape::Linear,
+            ));
+
+            value = next_value;
+            time += delta_time;
+        }
+
+        segments
+    }
+}
+
+impl<P: Process> DialMotion for SampledMotion<P> {
+    fn wander(&mut self, range: &DialRange, start: f32) -> VecDeque<PathSegment> {
+        self.sample(range, start, WANDER_SAMPLED_TIME)
+    }
+
+    fn drift_out(
+        &mut self,
+        range: &DialRange,
+        start: f32,
+        drift_out_time: f32,
+    ) -> VecDeque<PathSegment> {
+        // A needle that is out of the range comes back in first, so that it can leave on time
+        if !range.contains(start) {
+            let back = return_segment(
+                range,
+                start,
+                drift_out_time,
+                &self.segment_times,
+                SegmentShape::Linear,
+                &mut self.rng,
+            );
+            let mut segments = self.drift_out(range, back.end, drift_out_time - back.duration);
+            segments.push_front(back);
+
+            return segments;
+        }
+
+        let exit_time = self
+            .rng
+            .gen_range(self.segment_times.clone())
+            .min(drift_out_time);
+
+        let mut segments = self.sample(range, start, drift_out_time - exit_time);
+
+        let value = segments.back().map_or(start, |segment| segment.end);
+        segments.push_back(exit_segment(
+            range,
+            value,
+            exit_time,
+            SegmentShape::Linear,
+            &mut self.rng,
+        ));
+
+        segments
+    }
+}
+
+/// Random jitter that is pulled back towards the middle of the range
+struct OrnsteinUhlenbeck {
+    reversion: f32,
+    volatility: f32,
+}
+
+impl Process for OrnsteinUhlenbeck {
+    fn next_value(
+        &mut self,
+        range: &DialRange,
+        value: f32,
+        delta_time: f32,
+        rng: &mut ChaCha8Rng,
+    ) -> f32 {
+        let half_width = (range.end - range.start) / 2.0;
+        let offset = (value - range.middle()) / half_width;
+
+        let noise = standard_normal(rng);
+        let mut next_offset = offset - self.reversion * offset * delta_time
+            + self.volatility * delta_time.sqrt() * noise;
+
+        // Bounces off the edges of the range
+        if next_offset > 1.0 {
+            next_offset = 2.0 - next_offset;
+        } else if next_offset < -1.0 {
+            next_offset = -2.0 - next_offset;
+        }
+
+        range.middle() + next_offset.clamp(-1.0, 1.0) * half_width
+    }
+}
+
+/// A smooth swing back and forth around the middle of the range
+struct Sinusoid {
+    period: f32,
+    amplitude: f32,
+    /// The current point in the swing, in radians
+    phase: f32,
+}
+
+impl Process for Sinusoid {
+    fn next_value(
+        &mut self,
+        range: &DialRange,
+        _value: f32,
+        delta_time: f32,
+        _rng: &mut ChaCha8Rng,
+    ) -> f32 {
+        let half_width = (range.end - range.start) / 2.0;
+
+        self.phase = (self.phase + TAU * delta_time / self.period) % TAU;
+
+        range.middle() + self.amplitude * half_width * self.phase.sin()
+    }
+
+    fn resume(&mut self, range: &DialRange, value: f32) {
+        if self.amplitude <= 0.0 {
+            return;
+        }
+
+        let half_width = (range.end - range.start) / 2.0;
+        let offset = ((value - range.middle()) / (self.amplitude * half_width)).clamp(-1.0, 1.0);
+
+        // Keeps swinging in the same direction, from the point in the swing that the needle is at
+        self.phase = if self.phase.cos() >= 0.0 {
+            offset.asin()
+        } else {
+            std::f32::consts::PI - offset.asin()
+        };
+    }
+}
+
+/// A random value from the standard normal distribution, using the Box-Muller transform
+fn standard_normal(rng: &mut impl Rng) -> f32 {
+    // 1 - u is never zero so the log is always finite
+    let u: f32 = rng.gen();
+    let v: f32 = rng.gen();
+
+    (-2.0 * (1.0 - u).ln()).sqrt() * (TAU * v).cos()
+}
+
+/// A segment from `start`, which is out of `range`, back to a random point inside it
+///
+/// It takes at most half of `drift_out_time`, so that the rest of the time is left to leave the
+/// range again.
+fn return_segment(
+    range: &DialRange,
+    start: f32,
+    drift_out_time: f32,
+    segment_times: &RangeInclusive<f32>,
+    shape: SegmentShape,
+    rng: &mut impl Rng,
+) -> PathSegment {
+    let end = range.random_in(rng);
+    let duration = rng
+        .gen_range(segment_times.clone())
+        .min(drift_out_time / 2.0);
+
+    PathSegment::new(start, end, duration, shape)
+}
+
+/// A segment from `start`, which is inside `range`, that passes the end of the range exactly
+/// `exit_time` seconds later
+fn exit_segment(
+    range: &DialRange,
+    start: f32,
+    exit_time: f32,
+    shape: SegmentShape,
+    rng: &mut impl Rng,
+) -> PathSegment {
+    let mut unit = PathSegment::new(start, range.slightly_out(start, rng), 1.0, shape);
+
+    // From right by the edge of the range the needle would barely be moving as it leaves, so it
+    // crosses the range to leave from the other side instead
+    if time_to_pass_end(range, &unit) < EDGE_EXIT_FRACTION {
+        unit.end = range.start + range.end - unit.end;
+    }
+
+    let time_per_duration = time_to_pass_end(range, &unit);
+
+    PathSegment::new(start, unit.end, exit_time / time_per_duration, shape)
+}
+
+// Returns the time at which the dial needle will pass the end of the range while following
+// `segment` out of it
+fn time_to_pass_end(range: &DialRange, segment: &PathSegment) -> f32 {
+    let boundary = if segment.end < range.start {
+        range.start
+    } else {
+        range.end
+    };
+
+    segment.time_at_value(boundary)
+}
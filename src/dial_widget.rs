@@ -1,7 +1,7 @@
 use eframe::{
     egui,
-    emath::{Pos2, Vec2},
-    epaint::{CircleShape, Color32, Stroke},
+    emath::{Align2, Pos2, Vec2},
+    epaint::{CircleShape, Color32, FontId, Stroke},
 };
 
 use crate::dial::{DialRange, DIAL_MAX_VALUE};
@@ -19,21 +19,32 @@ const DIAL_ANGLE_OFFSET: f32 = -std::f32::consts::FRAC_PI_2;
 const DIAL_TICK_COLOR: Color32 = Color32::WHITE;
 const DIAL_NEEDLE_COLOR: Color32 = Color32::YELLOW;
 const DIAL_NEEDLE_FLASHING_COLOR: Color32 = Color32::from_rgb(0, 186, 255);
+/// The size of a dial's label text relative to the size of the dial
+const DIAL_LABEL_SIZE_PERCENT: f32 = 0.15;
+const DIAL_LABEL_COLOR: Color32 = Color32::WHITE;
 
-pub struct DialWidget {
+pub struct DialWidget<'a> {
     value: f32,
     radius: f32,
     in_range: DialRange,
     flashing: bool,
+    label: Option<&'a str>,
 }
 
-impl DialWidget {
-    pub fn new(value: f32, radius: f32, in_range: DialRange, flashing: bool) -> Self {
+impl<'a> DialWidget<'a> {
+    pub fn new(
+        value: f32,
+        radius: f32,
+        in_range: DialRange,
+        flashing: bool,
+        label: Option<&'a str>,
+    ) -> Self {
         Self {
             value,
             radius,
             in_range,
             flashing,
+            label,
         }
     }
 
@@ -109,6 +120,20 @@ impl DialWidget {
                     },
                 ),
             });
+
+            // Draw the label underneath the dial, outside of its ticks and in-range bar
+            if let Some(label) = self.label {
+                let label_pos = Pos2::new(center.x, rect.bottom() + DIAL_BAR_WIDTH * 2.0);
+                let font_id = FontId::proportional(rect.width() * DIAL_LABEL_SIZE_PERCENT);
+
+                painter.text(
+                    label_pos,
+                    Align2::CENTER_TOP,
+                    label,
+                    font_id,
+                    DIAL_LABEL_COLOR,
+                );
+            }
         }
 
         response.mark_changed();
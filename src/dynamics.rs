@@ -0,0 +1,584 @@
+use std::f32;
+
+use eframe::emath::Vec2;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::ball::{BallAxis, BallVelocity};
+
+/// Angle in radians around the crosshair to avoid randomly moving in
+const CROSSHAIR_AVOIDANCE_DEADZONE: f32 = f32::consts::FRAC_PI_2;
+
+/// How the participant's input moves the ball, by which derivative of its position the input sets
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlOrder {
+    /// Zero-order control: the input sets the ball's offset, like moving it by hand
+    Position,
+    /// First-order control: the input sets how fast the ball moves
+    #[default]
+    Velocity,
+    /// Second-order control: the input sets how fast the ball speeds up, so it keeps moving after
+    /// the input is released
+    Acceleration,
+}
+
+impl ControlOrder {
+    /// The gain used when the config doesn't give one
+    ///
+    /// This is units of offset, velocity or acceleration at full input, where 1.0 unit is half
+    /// the width of the tracking frame.
+    pub fn default_gain(self) -> f32 {
+        match self {
+            ControlOrder::Position => 0.5,
+            ControlOrder::Velocity => 1.2,
+            ControlOrder::Acceleration => 1.5,
+        }
+    }
+
+    /// Creates the dynamics for this control order
+    pub fn dynamics(self, gain: Option<f32>) -> Box<dyn ControlDynamics> {
+        let gain = gain.unwrap_or(self.default_gain());
+
+        match self {
+            ControlOrder::Position => Box::new(PositionControl {
+                gain,
+                last_offset: Vec2::ZERO,
+            }),
+            ControlOrder::Velocity => Box::new(VelocityControl { gain }),
+            ControlOrder::Acceleration => Box::new(AccelerationControl {
+                gain,
+                velocity: Vec2::ZERO,
+            }),
+        }
+    }
+}
+
+/// What moves the ball away from the crosshair, which the participant has to make up for
+///
+/// In [`TrackingMode::Pursuit`](crate::tracking::TrackingMode::Pursuit) this moves the target
+/// instead of the ball.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConfigDisturbance {
+    /// The ball drifts at the ball velocity, and changes direction at random times
+    #[default]
+    RandomDrift,
+    /// The ball is moved along a sum of sine waves on each axis, which each start at a random
+    /// phase
+    SumOfSines {
+        /// The frequency of each sine wave, in hertz
+        frequencies: Vec<f32>,
+        /// The amplitude of each sine wave, in the same order as the frequencies, where 1.0 is
+        /// half the width of the tracking frame
+        amplitudes: Vec<f32>,
+    },
+    /// The ball is moved in straight lines between waypoints
+    Scripted {
+        /// Each waypoint is `[time, x, y]`, with the time in seconds since the session started and
+        /// the position in the ball's coordinates, in order of time
+        waypoints: Vec<[f32; 3]>,
+        /// If the path starts again from the first waypoint after the last one, otherwise the ball
+        /// stays at the last waypoint
+        #[serde(default)]
+        repeat: bool,
+    },
+}
+
+impl ConfigDisturbance {
+    /// Creates the disturbance, where the random drift uses the ball's other settings
+    pub fn disturbance(
+        &self,
+        velocity: BallVelocity,
+        direction_change_time_min: f32,
+        direction_change_time_max: f32,
+        axis: BallAxis,
+    ) -> Box<dyn Disturbance> {
+        match self {
+            ConfigDisturbance::RandomDrift => Box::new(RandomDrift::new(
+                velocity.into(),
+                direction_change_time_min,
+                direction_change_time_max,
+                axis,
+            )),
+            ConfigDisturbance::SumOfSines {
+                frequencies,
+                amplitudes,
+            } => Box::new(SumOfSines::new(frequencies, amplitudes)),
+            ConfigDisturbance::Scripted { waypoints, repeat } => Box::new(ScriptedPath {
+                waypoints: waypoints.clone(),
+                repeat: *repeat,
+                time: 0.0,
+                last: Vec2::ZERO,
+                time_scale: 1.0,
+            }),
+        }
+    }
+}
+
+/// Moves the ball from the participant's input
+pub trait ControlDynamics: Send {
+    /// How far the ball is moved over `delta_time` seconds by `input`, in the ball's coordinates
+    fn step(&mut self, input: Vec2, delta_time: f32) -> Vec2;
+
+    /// The ball hit a wall, where `normal` points from the wall into the frame
+    fn hit_wall(&mut self, _normal: Vec2) {}
+}
+
+/// Moves the ball on its own, away from the crosshair
+pub trait Disturbance: Send {
+    /// How far the ball is moved over `delta_time` seconds, from `pos`
+    fn step(&mut self, pos: Vec2, delta_time: f32) -> Vec2;
+
+    /// The ball hit a wall, where `normal` points from the wall into the frame
+    fn hit_wall(&mut self, _normal: Vec2) {}
+
+    /// Makes the disturbance move the ball `speed_scale` times as fast, and change direction
+    /// `direction_change_scale` times as often
+    fn set_difficulty(&mut self, speed_scale: f32, direction_change_scale: f32);
+}
+
+/// Zero-order control, the ball is offset by the input times the gain
+struct PositionControl {
+    gain: f32,
+    /// The offset the input gave last step
+    last_offset: Vec2,
+}
+
+impl ControlDynamics for PositionControl {
+    fn step(&mut self, input: Vec2, _delta_time: f32) -> Vec2 {
+        let offset = input * self.gain;
+        offset - std::mem::replace(&mut self.last_offset, offset)
+    }
+}
+
+/// First-order control, the ball moves at the input times the gain
+struct VelocityControl {
+    gain: f32,
+}
+
+impl ControlDynamics for VelocityControl {
+    fn step(&mut self, input: Vec2, delta_time: f32) -> Vec2 {
+        input * self.gain * delta_time
+    }
+}
+
+/// Second-order control, the ball accelerates at the input times the gain
+struct AccelerationControl {
+    gain: f32,
+    velocity: Vec2,
+}
+
+impl ControlDynamics for AccelerationControl {
+    fn step(&mut self, input: Vec2, delta_time: f32) -> Vec2 {
+        self.velocity += input * self.gain * delta_time;
+        self.velocity * delta_time
+    }
+
+    fn hit_wall(&mut self, normal: Vec2) {
+        // The ball stops against the wall instead of building up speed into it
+        if normal.x * self.velocity.x < 0.0 {
+            self.velocity.x = 0.0;
+        }
+        if normal.y * self.velocity.y < 0.0 {
+            self.velocity.y = 0.0;
+        }
+    }
+}
+
+/// The ball drifts at a constant speed and changes direction at random times, bouncing off of
+/// the walls
+struct RandomDrift {
+    velocity: Vec2,
+
+    /// Keeps track of how long has the ball been running since last velocity change, sped up by
+    /// the direction change scale
+    time_running: f32,
+
+    /// There will be a random time where the ball is suppose to change it's velocity, from the
+    /// range given by the config file.
+    ///
+    /// For example any time from 1 second to 7 seconds.
+    velocity_change_time_at: f32,
+
+    /// The minimum value of the time range
+    direction_change_time_min: f32,
+
+    /// The maximum value of the time range
+    direction_change_time_max: f32,
+
+    /// Multiplies the ball's speed
+    speed_scale: f32,
+
+    /// Multiplies how quickly the time until the next change of direction runs out
+    direction_change_scale: f32,
+
+    /// The directions the ball can drift in
+    axis: BallAxis,
+}
+
+impl RandomDrift {
+    fn new(
+        speed: f32,
+        direction_change_time_min: f32,
+        direction_change_time_max: f32,
+        axis: BallAxis,
+    ) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let radians = rng.gen_range(0.0..f32::consts::TAU);
+        let direction = axis
+            .direction()
+            .unwrap_or(Vec2::new(radians.cos(), radians.sin()));
+
+        Self {
+            velocity: direction * speed,
+            time_running: 0.0,
+            velocity_change_time_at: 0.0,
+            direction_change_time_min,
+            direction_change_time_max,
+            speed_scale: 1.0,
+            direction_change_scale: 1.0,
+            axis,
+        }
+    }
+
+    /// Function to make calculate the new velocity.
+    /// This uses geometry where it can choose any velocity within a 360 degree angle of the ball.
+    ///
+    /// Note however that this takes into account the position of the ball relative to the crosshair.
+    /// The ball will never randomly change direction in a way that will bring it towards the crosshair.
+    /// This is done by limiting the random ball with a 90 degree deadzone for possible new angles.
+    fn new_vel(&self, pos: Vec2) -> Vec2 {
+        let mut rng = rand::thread_rng();
+
+        // Along a single axis the only direction that doesn't bring the ball towards the crosshair
+        // is straight away from it
+        if let Some(direction) = self.axis.direction() {
+            let offset = pos.dot(direction);
+            let sign = if offset != 0.0 {
+                offset.signum()
+            } else if rng.gen() {
+                1.0
+            } else {
+                -1.0
+            };
+
+            return direction * sign * self.velocity.length();
+        }
+
+        // The crosshair is positioned at (0, 0) in our coordinate system
+        // We add pi to get the angle from the ball to the crosshair, rather than the
+        // angle from the crosshair to the ball
+        let crosshair_angle = (pos.angle() + f32::consts::PI) % f32::consts::TAU;
+        let left_deadzone = ((crosshair_angle - CROSSHAIR_AVOIDANCE_DEADZONE / 2.0)
+            + f32::consts::TAU)
+            % f32::consts::TAU;
+        let right_deadzone = ((crosshair_angle + CROSSHAIR_AVOIDANCE_DEADZONE / 2.0)
+            + f32::consts::TAU)
+            % f32::consts::TAU;
+
+        let absolute_smallest_difference =
+            f32::consts::PI - ((left_deadzone - right_deadzone).abs() - f32::consts::PI).abs();
+        let absolute_largest_difference = f32::consts::TAU - absolute_smallest_difference;
+        let radians_offset = rng.gen_range(0.0..absolute_largest_difference);
+        let radians = right_deadzone + radians_offset;
+
+        let (x, y) = (radians.cos(), radians.sin());
+
+        Vec2::new(x * self.velocity.length(), y * self.velocity.length())
+    }
+}
+
+impl Disturbance for RandomDrift {
+    fn step(&mut self, pos: Vec2, delta_time: f32) -> Vec2 {
+        let mut rng = rand::thread_rng();
+
+        if self.time_running >= self.velocity_change_time_at {
+            self.velocity = self.new_vel(pos);
+
+            self.time_running = 0.0;
+            self.velocity_change_time_at =
+                rng.gen_range(self.direction_change_time_min..=self.direction_change_time_max);
+        }
+
+        self.time_running += delta_time * self.direction_change_scale;
+
+        self.velocity * self.speed_scale * delta_time
+    }
+
+    fn hit_wall(&mut self, normal: Vec2) {
+        // Bounces the velocity off of the wall
+        if normal.x != 0.0 {
+            self.velocity.x = normal.x * self.velocity.x.abs();
+        }
+        if normal.y != 0.0 {
+            self.velocity.y = normal.y * self.velocity.y.abs();
+        }
+    }
+
+    fn set_difficulty(&mut self, speed_scale: f32, direction_change_scale: f32) {
+        self.speed_scale = speed_scale;
+        self.direction_change_scale = direction_change_scale;
+    }
+}
+
+/// The ball follows a sum of sine waves on each axis, the classic disturbance for compensatory
+/// tracking because it is hard to predict but has a known spectrum
+struct SumOfSines {
+    /// The frequency in hertz, amplitude, and phases on each axis of every sine wave
+    waves: Vec<(f32, f32, Vec2)>,
+    /// The time along the waves, sped up by the direction change scale
+    time: f32,
+    speed_scale: f32,
+    direction_change_scale: f32,
+}
+
+impl SumOfSines {
+    fn new(frequencies: &[f32], amplitudes: &[f32]) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let waves = frequencies
+            .iter()
+            .zip(amplitudes)
+            .map(|(&frequency, &amplitude)| {
+                let phases = Vec2::new(
+                    rng.gen_range(0.0..f32::consts::TAU),
+                    rng.gen_range(0.0..f32::consts::TAU),
+                );
+                (frequency, amplitude, phases)
+            })
+            .collect();
+
+        Self {
+            waves,
+            time: 0.0,
+            speed_scale: 1.0,
+            direction_change_scale: 1.0,
+        }
+    }
+
+    /// The sum of the waves at `time`
+    fn offset(&self, time: f32) -> Vec2 {
+        self.waves
+            .iter()
+            .map(|&(frequency, amplitude, phases)| {
+                let angle = f32::consts::TAU * frequency * time;
+                Vec2::new(
+                    (angle + phases.x).sin() * amplitude,
+                    (angle + phases.y).sin() * amplitude,
+                )
+            })
+            .fold(Vec2::ZERO, |sum, wave| sum + wave)
+    }
+}
+
+impl Disturbance for SumOfSines {
+    fn step(&mut self, _pos: Vec2, delta_time: f32) -> Vec2 {
+        let start = self.offset(self.time);
+        self.time += delta_time * self.direction_change_scale;
+
+        (self.offset(self.time) - start) * self.speed_scale
+    }
+
+    fn set_difficulty(&mut self, speed_scale: f32, direction_change_scale: f32) {
+        self.speed_scale = speed_scale;
+        self.direction_change_scale = direction_change_scale;
+    }
+}
+
+/// The ball follows straight lines between waypoints at set times
+struct ScriptedPath {
+    waypoints: Vec<[f32; 3]>,
+    repeat: bool,
+    /// The time along the path, sped up by the difficulty
+    time: f32,
+    /// Where the path was last step, which starts at the center of the frame
+    last: Vec2,
+    /// Multiplies how quickly the time along the path runs
+    time_scale: f32,
+}
+
+impl ScriptedPath {
+    /// Where the path is at `time`
+    fn position(&self, time: f32) -> Vec2 {
+        let Some(&[end, ..]) = self.waypoints.last() else {
+            return Vec2::ZERO;
+        };
+
+        let time = if self.repeat && end > 0.0 {
+            time % end
+        } else {
+            time
+        };
+
+        // The index of the first waypoint after `time`
+        let next = self.waypoints.partition_point(|&[t, ..]| t <= time);
+
+        let point = |[_, x, y]: [f32; 3]| Vec2::new(x, y);
+
+        match (next.checked_sub(1), self.waypoints.get(next)) {
+            (Some(previous), Some(&after)) => {
+                let before = self.waypoints[previous];
+                let fraction = (time - before[0]) / (after[0] - before[0]);
+
+                point(before) + (point(after) - point(before)) * fraction
+            }
+            (Some(previous), None) => point(self.waypoints[previous]),
+            (None, _) => point(self.waypoints[0]),
+        }
+    }
+}
+
+impl Disturbance for ScriptedPath {
+    fn step(&mut self, _pos: Vec2, delta_time: f32) -> Vec2 {
+        self.time += delta_time * self.time_scale;
+
+        let position = self.position(self.time);
+        position - std::mem::replace(&mut self.last, position)
+    }
+
+    fn set_difficulty(&mut self, speed_scale: f32, direction_change_scale: f32) {
+        // A faster path along the same waypoints is the same path run in less time
+        self.time_scale = speed_scale * direction_change_scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA_TIME: f32 = 0.125;
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).length() < 1e-4,
+            "{actual:?} isn't {expected:?}"
+        );
+    }
+
+    /// How far the ball moves in total over `steps` steps of holding `input`
+    fn hold(dynamics: &mut dyn ControlDynamics, input: Vec2, steps: usize) -> Vec2 {
+        (0..steps).fold(Vec2::ZERO, |moved, _| {
+            moved + dynamics.step(input, DELTA_TIME)
+        })
+    }
+
+    fn path(waypoints: &[[f32; 3]], repeat: bool) -> ScriptedPath {
+        ScriptedPath {
+            waypoints: waypoints.to_vec(),
+            repeat,
+            time: 0.0,
+            last: Vec2::ZERO,
+            time_scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn position_control_offsets_the_ball_by_the_input() {
+        let mut dynamics = ControlOrder::Position.dynamics(Some(0.5));
+        let input = Vec2::new(1.0, -0.5);
+
+        assert_close(hold(dynamics.as_mut(), input, 1), Vec2::new(0.5, -0.25));
+        // Holding the input still doesn't move the ball any further
+        assert_close(hold(dynamics.as_mut(), input, 10), Vec2::ZERO);
+        // Releasing it puts the ball back
+        assert_close(
+            hold(dynamics.as_mut(), Vec2::ZERO, 1),
+            Vec2::new(-0.5, 0.25),
+        );
+    }
+
+    #[test]
+    fn velocity_control_moves_the_ball_at_the_input() {
+        let mut dynamics = ControlOrder::Velocity.dynamics(Some(2.0));
+        let input = Vec2::new(0.0, 1.0);
+
+        assert_close(hold(dynamics.as_mut(), input, 8), Vec2::new(0.0, 2.0));
+        assert_close(hold(dynamics.as_mut(), Vec2::ZERO, 8), Vec2::ZERO);
+    }
+
+    #[test]
+    fn acceleration_control_keeps_moving_after_the_input_is_released() {
+        let mut dynamics = ControlOrder::Acceleration.dynamics(Some(1.0));
+        hold(dynamics.as_mut(), Vec2::new(1.0, 0.0), 8);
+
+        // It has sped up to 1.0 after a second
+        assert_close(hold(dynamics.as_mut(), Vec2::ZERO, 8), Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn acceleration_control_stops_against_walls() {
+        let mut dynamics = ControlOrder::Acceleration.dynamics(Some(1.0));
+        hold(dynamics.as_mut(), Vec2::new(1.0, 1.0), 8);
+
+        // Hitting the right wall only stops the speed into it
+        dynamics.hit_wall(Vec2::new(-1.0, 0.0));
+        assert_close(hold(dynamics.as_mut(), Vec2::ZERO, 8), Vec2::new(0.0, 1.0));
+
+        // Moving away from a wall isn't stopped by it
+        dynamics.hit_wall(Vec2::new(0.0, 1.0));
+        assert_close(hold(dynamics.as_mut(), Vec2::ZERO, 8), Vec2::new(0.0, 1.0));
+
+        dynamics.hit_wall(Vec2::new(0.0, -1.0));
+        assert_close(hold(dynamics.as_mut(), Vec2::ZERO, 8), Vec2::ZERO);
+    }
+
+    #[test]
+    fn control_orders_fall_back_to_their_default_gains() {
+        for order in [
+            ControlOrder::Position,
+            ControlOrder::Velocity,
+            ControlOrder::Acceleration,
+        ] {
+            let mut default = order.dynamics(None);
+            let mut explicit = order.dynamics(Some(order.default_gain()));
+            let input = Vec2::new(0.5, 1.0);
+
+            assert_close(
+                hold(default.as_mut(), input, 4),
+                hold(explicit.as_mut(), input, 4),
+            );
+        }
+    }
+
+    #[test]
+    fn scripted_paths_interpolate_between_waypoints() {
+        let path = path(&[[1.0, 0.0, 0.0], [2.0, 1.0, -1.0], [4.0, 0.0, 1.0]], false);
+
+        // Before the first waypoint the path waits there
+        assert_close(path.position(0.0), Vec2::ZERO);
+        assert_close(path.position(1.5), Vec2::new(0.5, -0.5));
+        assert_close(path.position(2.0), Vec2::new(1.0, -1.0));
+        assert_close(path.position(3.5), Vec2::new(0.25, 0.5));
+        // After the last waypoint the path stays there
+        assert_close(path.position(10.0), Vec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn repeating_scripted_paths_start_again_after_the_last_waypoint() {
+        let path = path(&[[0.0, 0.0, 0.0], [2.0, 1.0, 0.0], [4.0, 0.0, 0.0]], true);
+
+        assert_close(path.position(1.0), Vec2::new(0.5, 0.0));
+        assert_close(path.position(5.0), Vec2::new(0.5, 0.0));
+        assert_close(path.position(10.0), Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn scripted_paths_without_waypoints_stay_at_the_center() {
+        assert_eq!(path(&[], true).position(1.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn scripted_steps_follow_the_path() {
+        let mut path = path(&[[0.0, 0.0, 0.0], [2.0, 1.0, 0.5]], false);
+        path.set_difficulty(2.0, 1.0);
+
+        let moved = (0..4).fold(Vec2::ZERO, |moved, _| {
+            moved + path.step(Vec2::ZERO, DELTA_TIME)
+        });
+
+        // Twice the speed gets through a second of the path in half a second
+        assert_close(moved, Vec2::new(0.5, 0.25));
+    }
+}
@@ -0,0 +1,408 @@
+use anyhow::{bail, Result};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{ConfigDialRow, ConfigTrial},
+    dial::OutOfRange,
+    response::ResponseInput,
+    tracking_widget::FeedbackColor,
+};
+
+/// Describes how to generate a list of trials instead of writing each one by hand
+///
+/// The same seed always expands into the same trials.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigGenerator {
+    /// The seed for the random number generator. If left out, a random seed is chosen and logged
+    pub seed: Option<u64>,
+
+    /// The number of trials to generate
+    pub num_trials: usize,
+
+    /// The dials that trials may use, and how likely each one is to be picked
+    pub dials: Vec<WeightedName>,
+
+    /// The alarms that trials may use, and how likely each one is to be picked
+    pub alarms: Vec<WeightedName>,
+
+    /// How the time until each trial's alarm is chosen
+    pub alarm_time: AlarmTimeDistribution,
+
+    /// How the correct response key is chosen for each trial
+    pub response_key: KeyRule,
+
+    /// If true, the same dial is never picked for two trials in a row
+    #[serde(default)]
+    pub no_repeat_dial: bool,
+
+    /// Text to display after a correct key was pressed, for every generated trial
+    pub feedback_text_correct: Option<String>,
+
+    /// Text to display after an incorrect key was pressed, for every generated trial
+    pub feedback_text_incorrect: Option<String>,
+
+    /// Tracking frame color after a correct key was pressed, for every generated trial
+    pub feedback_color_correct: Option<FeedbackColor>,
+
+    /// Tracking frame color after an incorrect key was pressed, for every generated trial
+    pub feedback_color_incorrect: Option<FeedbackColor>,
+
+    /// What the needle does after it leaves the range, for every generated trial
+    pub out_of_range: Option<OutOfRange>,
+
+    /// Seconds after the alarm until the needle returns on its own, for every generated trial
+    pub recover_after: Option<f32>,
+}
+
+/// The name of a dial or alarm along with its relative chance of being picked
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeightedName {
+    pub name: String,
+
+    /// The relative chance of this entry being picked, compared to the others in the same list
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// The random distribution that alarm times are drawn from, in seconds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(tag = "distribution", rename_all = "lowercase")]
+pub enum AlarmTimeDistribution {
+    /// Any time between `min` and `max` is equally likely
+    Uniform { min: f32, max: f32 },
+    /// Exponentially distributed times with the given mean, shifted later by `offset`
+    Exponential {
+        mean: f32,
+        #[serde(default)]
+        offset: f32,
+    },
+    /// Every trial uses the same alarm time
+    Fixed { value: f32 },
+}
+
+impl AlarmTimeDistribution {
+    fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            AlarmTimeDistribution::Uniform { min, max } => rng.gen_range(min..=max),
+            AlarmTimeDistribution::Exponential { mean, offset } => {
+                // Inverse transform sampling, 1 - u is never zero so the log is always finite
+                let u: f32 = rng.gen();
+                offset - mean * (1.0 - u).ln()
+            }
+            AlarmTimeDistribution::Fixed { value } => value,
+        }
+    }
+}
+
+/// How the correct response key of a generated trial is chosen
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRule {
+    /// The key is the number of the trial's dial, counting from 1 in reading order across the rows
+    DialIndex,
+    /// Every trial uses the same key or button
+    Fixed(ResponseInput),
+    /// The key is the [`ConfigDial::response_key`](crate::config::ConfigDial::response_key) of
+    /// the trial's dial
+    Dial,
+}
+
+impl ConfigGenerator {
+    /// Expands this generator into its list of trials
+    ///
+    /// `dial_rows` is the dial layout, which is needed by [`KeyRule::DialIndex`].
+    pub fn generate(&self, dial_rows: &[ConfigDialRow]) -> Result<Vec<ConfigTrial>> {
+        self.check(dial_rows)?;
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        log::info!("generating {} trials with seed {seed}", self.num_trials);
+
+        let dial_weights: Vec<_> = self.dials.iter().map(|d| d.weight).collect();
+        let alarms = WeightedIndex::new(self.alarms.iter().map(|a| a.weight))?;
+
+        let mut last_dial = None;
+        let mut trials = Vec::with_capacity(self.num_trials);
+
+        for trial_num in 1..=self.num_trials {
+            let dial_index = match last_dial {
+                // The last dial's weight is zeroed so that it can't be picked again
+                Some(last) if self.no_repeat_dial => {
+                    let mut weights = dial_weights.clone();
+                    weights[last] = 0.0;
+                    WeightedIndex::new(weights)?.sample(&mut rng)
+                }
+                _ => WeightedIndex::new(&dial_weights)?.sample(&mut rng),
+            };
+            last_dial = Some(dial_index);
+
+            let dial = &self.dials[dial_index].name;
+            let alarm = &self.alarms[alarms.sample(&mut rng)].name;
+            let alarm_time = self.alarm_time.sample(&mut rng);
+
+            let correct_response_key = match self.response_key {
+                KeyRule::DialIndex => {
+                    let position = dial_rows
+                        .iter()
+                        .flat_map(|r| r.dials.iter())
+                        .position(|d| &d.name == dial)
+                        .unwrap();
+                    ResponseInput::digit(position as u32 + 1)
+                }
+                KeyRule::Fixed(key) => Some(key),
+                KeyRule::Dial => None,
+            };
+
+            let key_text = correct_response_key
+                .map_or_else(|| String::from("of the dial"), |k| format!("`{k}`"));
+
+            log::info!(
+                "generated trial #{trial_num}: dial `{dial}`, alarm `{alarm}`, alarm time {alarm_time:.3}, key {key_text}"
+            );
+
+            trials.push(ConfigTrial {
+                correct_response_key,
+                feedback_text_correct: self.feedback_text_correct.clone(),
+                feedback_text_incorrect: self.feedback_text_incorrect.clone(),
+                feedback_color_correct: self.feedback_color_correct.clone(),
+                feedback_color_incorrect: self.feedback_color_incorrect.clone(),
+                dial: dial.clone(),
+                alarm: alarm.clone(),
+                alarm_time: Some(alarm_time),
+                out_of_range: self.out_of_range,
+                recover_after: self.recover_after,
+            });
+        }
+
+        Ok(trials)
+    }
+
+    /// Checks that this generator is able to generate trials for the given dial layout
+    fn check(&self, dial_rows: &[ConfigDialRow]) -> Result<()> {
+        let dial_names: Vec<_> = dial_rows
+            .iter()
+            .flat_map(|r| r.dials.iter().map(|d| &d.name))
+            .collect();
+
+        if self.dials.is_empty() || self.alarms.is_empty() {
+            bail!("The trial generator needs at least one dial and one alarm to pick from");
+        }
+
+        for entry in self.dials.iter().chain(self.alarms.iter()) {
+            if !(entry.weight >= 0.0 && entry.weight.is_finite()) {
+                bail!(
+                    "The trial generator weight for `{}` must be a positive number",
+                    entry.name
+                );
+            }
+        }
+
+        for dial in &self.dials {
+            if !dial_names.contains(&&dial.name) {
+                bail!(
+                    "The trial generator uses dial `{}`, which is missing!\nAvailable dials are: {dial_names:?}",
+                    dial.name
+                );
+            }
+        }
+
+        let pickable_dials = self.dials.iter().filter(|d| d.weight > 0.0).count();
+
+        if pickable_dials == 0 || self.alarms.iter().all(|a| a.weight == 0.0) {
+            bail!("The trial generator needs at least one dial and one alarm with a weight above zero");
+        }
+
+        if self.no_repeat_dial && pickable_dials < 2 && self.num_trials > 1 {
+            bail!("The trial generator needs at least two dials with a weight above zero to use `no_repeat_dial`");
+        }
+
+        if let KeyRule::DialIndex = self.response_key {
+            if dial_names.len() > 9 {
+                bail!(
+                    "The trial generator can only use `dial_index` keys with at most 9 dials, but there are {}",
+                    dial_names.len()
+                );
+            }
+        }
+
+        match self.alarm_time {
+            AlarmTimeDistribution::Uniform { min, max }
+                if !(max.is_finite() && min >= 0.0 && min <= max) =>
+            {
+                bail!("The trial generator's uniform alarm time needs finite 0.0 <= min <= max")
+            }
+            AlarmTimeDistribution::Exponential { mean, offset }
+                if !(mean.is_finite() && offset.is_finite() && mean > 0.0 && offset >= 0.0) =>
+            {
+                bail!("The trial generator's exponential alarm time needs finite mean > 0.0 and offset >= 0.0")
+            }
+            AlarmTimeDistribution::Fixed { value } if !(value.is_finite() && value >= 0.0) => {
+                bail!("The trial generator's fixed alarm time must be finite and not negative")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three dials in one row, named `d1` to `d3`
+    fn dial_rows() -> Vec<ConfigDialRow> {
+        #[derive(Deserialize)]
+        struct Rows {
+            row: Vec<ConfigDialRow>,
+        }
+
+        let rows: Rows = toml::from_str(
+            r#"
+                [[row]]
+                dial = [
+                    { name = "d1", range_start = 4000.0, range_end = 6000.0, speed = "medium" },
+                    { name = "d2", range_start = 4000.0, range_end = 6000.0, speed = "medium" },
+                    { name = "d3", range_start = 4000.0, range_end = 6000.0, speed = "medium" },
+                ]
+            "#,
+        )
+        .unwrap();
+
+        rows.row
+    }
+
+    /// A generator that picks from every dial, with `extra` added to its settings
+    fn generator(extra: &str) -> ConfigGenerator {
+        toml::from_str(&format!(
+            r#"
+                num_trials = 200
+                dials = [{{ name = "d1" }}, {{ name = "d2", weight = 2.0 }}, {{ name = "d3" }}]
+                alarms = [{{ name = "a1" }}, {{ name = "a2" }}]
+                {extra}
+            "#
+        ))
+        .unwrap()
+    }
+
+    /// The parts of each trial that are picked at random
+    fn summary(trials: &[ConfigTrial]) -> Vec<(String, String, Option<f32>)> {
+        trials
+            .iter()
+            .map(|t| (t.dial.clone(), t.alarm.clone(), t.alarm_time))
+            .collect()
+    }
+
+    const UNIFORM_DIAL_INDEX: &str = r#"
+        alarm_time = { distribution = "uniform", min = 2.0, max = 8.0 }
+        response_key = "dial_index"
+    "#;
+
+    #[test]
+    fn the_same_seed_generates_the_same_trials() {
+        let rows = dial_rows();
+        let generate = |seed: u64| {
+            generator(&format!("seed = {seed}\n{UNIFORM_DIAL_INDEX}"))
+                .generate(&rows)
+                .unwrap()
+        };
+
+        let trials = generate(7);
+
+        assert_eq!(trials.len(), 200);
+        assert_eq!(summary(&trials), summary(&generate(7)));
+        assert_ne!(summary(&trials), summary(&generate(8)));
+    }
+
+    #[test]
+    fn trials_follow_the_generator_settings() {
+        let rows = dial_rows();
+        let trials = generator(&format!("seed = 1\n{UNIFORM_DIAL_INDEX}"))
+            .generate(&rows)
+            .unwrap();
+
+        for trial in &trials {
+            let alarm_time = trial.alarm_time.unwrap();
+            assert!((2.0..=8.0).contains(&alarm_time), "{alarm_time}");
+
+            let dial_number = trial.dial[1..].parse().unwrap();
+            assert_eq!(
+                trial.correct_response_key,
+                ResponseInput::digit(dial_number)
+            );
+        }
+
+        // `d2` has twice the weight of the others
+        let count = |dial: &str| trials.iter().filter(|t| t.dial == dial).count();
+        assert!(count("d2") > count("d1") && count("d2") > count("d3"));
+    }
+
+    #[test]
+    fn alarm_time_distributions_keep_to_their_bounds() {
+        let rows = dial_rows();
+        let alarm_times = |distribution: &str| -> Vec<f32> {
+            let trials = generator(&format!(
+                "seed = 3\nalarm_time = {distribution}\nresponse_key = \"dial_index\""
+            ))
+            .generate(&rows)
+            .unwrap();
+
+            trials.iter().map(|t| t.alarm_time.unwrap()).collect()
+        };
+
+        let exponential =
+            alarm_times(r#"{ distribution = "exponential", mean = 2.0, offset = 1.5 }"#);
+        assert!(exponential.iter().all(|&time| time >= 1.5));
+        let mean = exponential.iter().sum::<f32>() / exponential.len() as f32;
+        assert!((mean - 3.5).abs() < 0.5, "{mean}");
+
+        let fixed = alarm_times(r#"{ distribution = "fixed", value = 4.0 }"#);
+        assert!(fixed.iter().all(|&time| time == 4.0));
+    }
+
+    #[test]
+    fn no_repeat_dial_never_picks_the_same_dial_twice_in_a_row() {
+        let rows = dial_rows();
+
+        for seed in 0..10 {
+            let trials = generator(&format!(
+                "seed = {seed}\nno_repeat_dial = true\n{UNIFORM_DIAL_INDEX}"
+            ))
+            .generate(&rows)
+            .unwrap();
+
+            for pair in trials.windows(2) {
+                assert_ne!(pair[0].dial, pair[1].dial, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn no_repeat_dial_needs_two_pickable_dials() {
+        let mut generator = generator(&format!("no_repeat_dial = true\n{UNIFORM_DIAL_INDEX}"));
+        generator.dials[0].weight = 0.0;
+        generator.dials[1].weight = 0.0;
+
+        assert!(generator.generate(&dial_rows()).is_err());
+    }
+
+    #[test]
+    fn alarm_time_distributions_must_be_finite() {
+        for distribution in [
+            r#"{ distribution = "uniform", min = 2.0, max = inf }"#,
+            r#"{ distribution = "exponential", mean = inf }"#,
+            r#"{ distribution = "exponential", mean = 2.0, offset = nan }"#,
+            r#"{ distribution = "fixed", value = inf }"#,
+        ] {
+            let generator = generator(&format!(
+                "alarm_time = {distribution}\nresponse_key = \"dial_index\""
+            ));
+            assert!(generator.generate(&dial_rows()).is_err(), "{distribution}");
+        }
+    }
+}
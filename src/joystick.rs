@@ -0,0 +1,250 @@
+use eframe::emath::Vec2;
+use gilrs::{ev::Axis, Gamepad, GamepadId};
+use serde::{Deserialize, Serialize};
+
+/// What happened to a gamepad, as recorded in the session metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceEventKind {
+    /// The gamepad was connected when the session started and was chosen for tracking
+    Selected,
+    /// The gamepad was plugged in during the session and was chosen for tracking
+    Connected,
+    /// The gamepad that was being used for tracking was unplugged
+    Disconnected,
+}
+
+/// A change to the gamepad that is used for tracking
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceEvent {
+    pub event: DeviceEventKind,
+    /// Seconds since the session started
+    pub time: f32,
+    /// The ID of the gamepad, as written to the log
+    pub id: usize,
+    pub name: String,
+    /// The UUID of the gamepad, in hex
+    pub uuid: String,
+}
+
+impl DeviceEvent {
+    pub fn new(event: DeviceEventKind, time: f32, id: GamepadId, gamepad: &Gamepad) -> Self {
+        Self {
+            event,
+            time,
+            id: id.into(),
+            name: gamepad.name().to_string(),
+            uuid: gamepad.uuid().iter().map(|b| format!("{b:02x}")).collect(),
+        }
+    }
+}
+
+/// The joystick axes that can be used to move the ball
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JoystickAxis {
+    LeftStickX,
+    LeftStickY,
+    LeftZ,
+    RightStickX,
+    RightStickY,
+    RightZ,
+    DPadX,
+    DPadY,
+}
+
+impl From<JoystickAxis> for Axis {
+    fn from(value: JoystickAxis) -> Self {
+        match value {
+            JoystickAxis::LeftStickX => Axis::LeftStickX,
+            JoystickAxis::LeftStickY => Axis::LeftStickY,
+            JoystickAxis::LeftZ => Axis::LeftZ,
+            JoystickAxis::RightStickX => Axis::RightStickX,
+            JoystickAxis::RightStickY => Axis::RightStickY,
+            JoystickAxis::RightZ => Axis::RightZ,
+            JoystickAxis::DPadX => Axis::DPadX,
+            JoystickAxis::DPadY => Axis::DPadY,
+        }
+    }
+}
+
+/// How the joystick's deflection is turned into how hard the ball is pushed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseCurve {
+    /// The ball is pushed in direct proportion to the deflection
+    #[default]
+    Linear,
+    /// The deflection is squared, giving finer control near the center of the joystick
+    Quadratic,
+}
+
+/// Settings for the joystick used in [`InputMode::Joystick`](crate::config::InputMode::Joystick)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfigJoystick {
+    /// Only use gamepads whose name contains this text, ignoring case
+    pub gamepad_name: Option<String>,
+
+    /// Only use the gamepad with this ID, as written to the log when the program starts
+    pub gamepad_id: Option<usize>,
+
+    /// The axis that moves the ball left and right
+    pub x_axis: JoystickAxis,
+
+    /// The axis that moves the ball up and down
+    pub y_axis: JoystickAxis,
+
+    /// Deflections smaller than this, from 0.0 to 1.0, are ignored
+    pub deadzone: f32,
+
+    /// [`ResponseCurve`]
+    pub curve: ResponseCurve,
+
+    /// Multiplies how hard the ball is pushed left and right
+    pub x_gain: f32,
+
+    /// Multiplies how hard the ball is pushed up and down
+    pub y_gain: f32,
+
+    /// Reverses the left and right direction
+    pub invert_x: bool,
+
+    /// Reverses the up and down direction
+    pub invert_y: bool,
+}
+
+impl Default for ConfigJoystick {
+    fn default() -> Self {
+        Self {
+            gamepad_name: None,
+            gamepad_id: None,
+            x_axis: JoystickAxis::LeftStickX,
+            y_axis: JoystickAxis::LeftStickY,
+            deadzone: 0.0,
+            curve: ResponseCurve::default(),
+            x_gain: 1.0,
+            y_gain: 1.0,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+impl ConfigJoystick {
+    /// Returns true if events from this gamepad should be used
+    pub fn selects(&self, id: GamepadId, gamepad: &Gamepad) -> bool {
+        let id_matches = self.gamepad_id.is_none_or(|i| i == usize::from(id));
+        let name_matches = self
+            .gamepad_name
+            .as_ref()
+            .is_none_or(|name| gamepad.name().to_lowercase().contains(&name.to_lowercase()));
+
+        id_matches && name_matches
+    }
+
+    /// Turns the raw axis values from the joystick into input axes for the ball, by applying the
+    /// deadzone, response curve, gain and inversion
+    pub fn apply(&self, raw_axes: Vec2) -> Vec2 {
+        let shape = |value: f32, gain: f32, invert: bool| {
+            let magnitude = value.abs().min(1.0);
+
+            // Rescale so that the output still starts at zero at the edge of the deadzone
+            let magnitude = if magnitude <= self.deadzone {
+                0.0
+            } else {
+                (magnitude - self.deadzone) / (1.0 - self.deadzone)
+            };
+
+            let magnitude = match self.curve {
+                ResponseCurve::Linear => magnitude,
+                ResponseCurve::Quadratic => magnitude * magnitude,
+            };
+
+            let sign = if invert {
+                -value.signum()
+            } else {
+                value.signum()
+            };
+
+            sign * magnitude * gain
+        };
+
+        Vec2::new(
+            shape(raw_axes.x, self.x_gain, self.invert_x),
+            shape(raw_axes.y, self.y_gain, self.invert_y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).length() < 1e-6,
+            "{actual:?} isn't {expected:?}"
+        );
+    }
+
+    #[test]
+    fn the_defaults_pass_axes_through() {
+        let joystick = ConfigJoystick::default();
+
+        for axes in [Vec2::ZERO, Vec2::new(0.3, -0.7), Vec2::new(-1.0, 1.0)] {
+            assert_close(joystick.apply(axes), axes);
+        }
+    }
+
+    #[test]
+    fn the_deadzone_is_cut_out_and_the_rest_rescaled() {
+        let joystick = ConfigJoystick {
+            deadzone: 0.2,
+            ..Default::default()
+        };
+
+        assert_close(joystick.apply(Vec2::new(0.1, -0.2)), Vec2::ZERO);
+        assert_close(joystick.apply(Vec2::new(0.6, -0.6)), Vec2::new(0.5, -0.5));
+        assert_close(joystick.apply(Vec2::new(1.0, -1.0)), Vec2::new(1.0, -1.0));
+    }
+
+    #[test]
+    fn the_quadratic_curve_keeps_the_direction() {
+        let joystick = ConfigJoystick {
+            curve: ResponseCurve::Quadratic,
+            ..Default::default()
+        };
+
+        assert_close(joystick.apply(Vec2::new(0.5, -0.5)), Vec2::new(0.25, -0.25));
+        assert_close(joystick.apply(Vec2::new(1.0, -1.0)), Vec2::new(1.0, -1.0));
+    }
+
+    #[test]
+    fn gain_and_inversion_apply_to_each_axis() {
+        let joystick = ConfigJoystick {
+            x_gain: 2.0,
+            y_gain: 0.5,
+            invert_y: true,
+            ..Default::default()
+        };
+
+        assert_close(joystick.apply(Vec2::new(0.4, 0.4)), Vec2::new(0.8, -0.2));
+        assert_close(joystick.apply(Vec2::new(-0.4, -0.4)), Vec2::new(-0.8, 0.2));
+    }
+
+    #[test]
+    fn everything_applies_in_order() {
+        let joystick = ConfigJoystick {
+            deadzone: 0.5,
+            curve: ResponseCurve::Quadratic,
+            x_gain: 3.0,
+            invert_x: true,
+            ..Default::default()
+        };
+
+        // (0.75 - 0.5) / 0.5 = 0.5, squared is 0.25, then tripled and inverted
+        assert_close(joystick.apply(Vec2::new(0.75, 0.0)), Vec2::new(-0.75, 0.0));
+        // Deflections past the edge are treated as full
+        assert_close(joystick.apply(Vec2::new(-1.5, 0.0)), Vec2::new(3.0, 0.0));
+    }
+}
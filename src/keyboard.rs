@@ -0,0 +1,185 @@
+use eframe::{egui::Key, emath::Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::response::ResponseInput;
+
+/// Settings for the keys used in [`InputMode::Keyboard`](crate::config::InputMode::Keyboard)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfigKeyboard {
+    /// The key that pushes the ball up
+    pub up: ResponseInput,
+
+    /// The key that pushes the ball down
+    pub down: ResponseInput,
+
+    /// The key that pushes the ball left
+    pub left: ResponseInput,
+
+    /// The key that pushes the ball right
+    pub right: ResponseInput,
+
+    /// Seconds for a held key to go from not pushing the ball to pushing it fully, or 0.0 to push
+    /// fully straight away
+    pub ramp_up_time: f32,
+
+    /// Seconds for a released key to stop pushing the ball, or 0.0 to stop straight away
+    pub ramp_down_time: f32,
+}
+
+impl Default for ConfigKeyboard {
+    fn default() -> Self {
+        Self {
+            up: ResponseInput::Key(Key::ArrowUp),
+            down: ResponseInput::Key(Key::ArrowDown),
+            left: ResponseInput::Key(Key::ArrowLeft),
+            right: ResponseInput::Key(Key::ArrowRight),
+            ramp_up_time: 0.0,
+            ramp_down_time: 0.0,
+        }
+    }
+}
+
+impl ConfigKeyboard {
+    /// The keys that move the ball, in the order up, down, left, right
+    pub fn tracking_keys(&self) -> [ResponseInput; 4] {
+        [self.up, self.down, self.left, self.right]
+    }
+
+    /// Returns true if this input moves the ball, rather than responding to alarms
+    pub fn is_tracking_key(&self, input: ResponseInput) -> bool {
+        self.tracking_keys().contains(&input)
+    }
+
+    /// Moves the current input axes towards the axes of the keys being held, over the configured
+    /// ramp times
+    ///
+    /// Reversing direction first ramps down to nothing, then ramps up in the new direction.
+    pub fn ramp(&self, current: Vec2, target: Vec2, delta_time: f32) -> Vec2 {
+        if self.ramp_up_time <= 0.0 && self.ramp_down_time <= 0.0 {
+            return target;
+        }
+
+        let ramp_axis = |current: f32, target: f32| {
+            let reversing = current * target < 0.0;
+            let (goal, time) = if reversing || target.abs() < current.abs() {
+                (if reversing { 0.0 } else { target }, self.ramp_down_time)
+            } else {
+                (target, self.ramp_up_time)
+            };
+
+            if time <= 0.0 {
+                return goal;
+            }
+
+            let max_change = delta_time / time;
+            current + (goal - current).clamp(-max_change, max_change)
+        };
+
+        Vec2::new(
+            ramp_axis(current.x, target.x),
+            ramp_axis(current.y, target.y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyboard(ramp_up_time: f32, ramp_down_time: f32) -> ConfigKeyboard {
+        ConfigKeyboard {
+            ramp_up_time,
+            ramp_down_time,
+            ..Default::default()
+        }
+    }
+
+    /// The axes after ramping towards `target` for `steps` steps of `delta_time`
+    fn ramp_for(
+        keyboard: &ConfigKeyboard,
+        mut current: Vec2,
+        target: Vec2,
+        steps: usize,
+        delta_time: f32,
+    ) -> Vec2 {
+        for _ in 0..steps {
+            current = keyboard.ramp(current, target, delta_time);
+        }
+
+        current
+    }
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).length() < 1e-4,
+            "{actual:?} isn't {expected:?}"
+        );
+    }
+
+    #[test]
+    fn without_ramp_times_keys_push_straight_away() {
+        let target = Vec2::new(1.0, -1.0);
+
+        assert_eq!(keyboard(0.0, 0.0).ramp(Vec2::ZERO, target, 0.01), target);
+        assert_eq!(
+            keyboard(0.0, 0.0).ramp(target, Vec2::ZERO, 0.01),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn held_keys_ramp_up_over_the_ramp_up_time() {
+        let keyboard = keyboard(0.5, 0.0);
+        let target = Vec2::new(1.0, 0.0);
+
+        assert_close(
+            ramp_for(&keyboard, Vec2::ZERO, target, 25, 0.01),
+            Vec2::new(0.5, 0.0),
+        );
+        assert_close(ramp_for(&keyboard, Vec2::ZERO, target, 50, 0.01), target);
+        // It stops once it is pushing fully
+        assert_close(ramp_for(&keyboard, Vec2::ZERO, target, 80, 0.01), target);
+    }
+
+    #[test]
+    fn released_keys_ramp_down_over_the_ramp_down_time() {
+        let keyboard = keyboard(0.0, 0.2);
+        let start = Vec2::new(0.0, -1.0);
+
+        assert_close(
+            ramp_for(&keyboard, start, Vec2::ZERO, 10, 0.01),
+            Vec2::new(0.0, -0.5),
+        );
+        assert_close(ramp_for(&keyboard, start, Vec2::ZERO, 20, 0.01), Vec2::ZERO);
+        // Pressing a key again pushes fully straight away
+        assert_eq!(keyboard.ramp(Vec2::ZERO, start, 0.01), start);
+    }
+
+    #[test]
+    fn reversing_ramps_down_before_ramping_up() {
+        let keyboard = keyboard(0.4, 0.2);
+        let start = Vec2::new(1.0, 0.0);
+        let target = Vec2::new(-1.0, 0.0);
+
+        // Ramping down from 1.0 takes 0.2 seconds, then ramping up takes 0.4
+        assert_close(
+            ramp_for(&keyboard, start, target, 10, 0.01),
+            Vec2::new(0.5, 0.0),
+        );
+        assert_close(ramp_for(&keyboard, start, target, 20, 0.01), Vec2::ZERO);
+        assert_close(
+            ramp_for(&keyboard, start, target, 40, 0.01),
+            Vec2::new(-0.5, 0.0),
+        );
+        assert_close(ramp_for(&keyboard, start, target, 60, 0.01), target);
+    }
+
+    #[test]
+    fn tracking_keys_are_recognised() {
+        let keyboard = ConfigKeyboard::default();
+
+        assert!(keyboard.is_tracking_key(ResponseInput::Key(Key::ArrowLeft)));
+        assert!(!keyboard.is_tracking_key(ResponseInput::Key(Key::A)));
+    }
+}
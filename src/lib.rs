@@ -1,82 +1,110 @@
 use anyhow::{bail, Result};
-use app::{AppState, DialsApp};
+use app::DialsApp;
 use audio::AudioManager;
-use dial::{Dial, DialRange};
-use eframe::epaint::Vec2;
-use lazy_static::lazy_static;
-use output::SessionOutput;
+use model::{Model, SnapshotCell};
 use std::{
-    collections::HashMap,
-    sync::Mutex,
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    path::Path,
+    sync::mpsc,
     thread,
-    time::{Duration, Instant},
 };
 
-use crate::ball::Ball;
-use crate::output::TrialReaction;
-use gilrs::{Event, Gilrs};
+use crate::{
+    ball::BallVelocity,
+    dial::{DialRange, DialSpeed, OutOfRange},
+    dial_motion::ConfigDialMotion,
+    dynamics::ConfigDisturbance,
+    response::ResponseInput,
+    tracking::TrackingMode,
+    tracking_widget::TrackingView,
+};
+use gilrs::Gilrs;
 
+mod adaptive;
 mod app;
 mod audio;
 mod ball;
+pub mod cli;
 mod config;
+mod config_loader;
 mod dial;
+mod dial_motion;
 mod dial_widget;
 mod dialog_popup;
+mod dynamics;
+mod generator;
+mod joystick;
+mod keyboard;
+mod metrics;
+mod model;
 mod output;
+mod response;
+mod timing;
+mod tracking;
 mod tracking_widget;
+mod window;
 
 /// The default path to the program configuration file
 pub const DEFAULT_CONFIG_PATH: &str = "./config.toml";
 /// The default path for the program's trial output CSV
 pub const DEFAULT_OUTPUT_PATH: &str = "./trial.csv";
 
-lazy_static! {
-    /// The global state of the application
-    static ref STATE: Mutex<AppState> = Mutex::new(AppState::default());
+/// Writes the commented configuration template to `path`
+///
+/// This refuses to replace an existing file unless `force` is set.
+pub fn init(path: &str, force: bool) -> Result<()> {
+    if !force && Path::new(path).exists() {
+        bail!("`{path}` already exists, pass `--force` to overwrite it");
+    }
+
+    std::fs::write(path, config::CONFIG_TEMPLATE)?;
+    println!("Wrote configuration template to `{path}`");
+
+    Ok(())
 }
 
-/// Creates a new [`eframe`] window, and spawns worker threads to run the dials research application
+/// Creates the session window, and spawns worker threads to run the dials research application
 ///
-/// This can fail if the configuration file is invalid, audio files cannot be loaded, or audio playback issues.
-pub fn run() -> Result<()> {
-    // Parse or generate the configuration file
-    let mut config = if let Ok(toml) = std::fs::read_to_string(DEFAULT_CONFIG_PATH) {
-        match toml::from_str(&toml) {
-            Ok(t) => t,
-            Err(e) => {
-                dialog_popup::show(
-                    "Configuration Error",
-                    "Failed to parse configuration file",
-                    format!("{e}"),
-                )
-                .unwrap();
-
-                bail!("Failed to parse configuration file: {}", e);
-            }
-        }
+/// The configuration is read from `config_path`, unless `use_default_config` is set, in which case
+/// the built-in demo session is run instead.
+///
+/// This can fail if the configuration file is missing or invalid, audio files cannot be loaded, or
+/// audio playback issues.
+pub fn run(config_path: &str, use_default_config: bool) -> Result<()> {
+    let mut config = if use_default_config {
+        log::info!("running with the built-in demo configuration");
+        config::Config::default()
     } else {
-        // Write out default config if none existed before
-        let config = config::Config::default();
-        let toml = toml::to_string(&config)?;
-        std::fs::write(DEFAULT_CONFIG_PATH, toml)?;
-
-        config
+        load_config(config_path)?
     };
 
     validate_config(&mut config)?;
 
     let audio = AudioManager::new()?;
 
-    // Maps alarm names to alarm structs
-    let alarms: HashMap<String, config::ConfigAlarm> = config
-        .alarms
-        .drain(..)
-        .map(|d| (d.name.clone(), d))
-        .collect();
+    // Gamepads are only required when they are used to move the ball, otherwise the session can
+    // still be run without them
+    let gilrs = match Gilrs::new() {
+        Ok(gilrs) => Some(gilrs),
+        Err(e) if matches!(config.input_mode, config::InputMode::Joystick) => {
+            dialog_popup::show(
+                "Joystick Error",
+                "Failed to initialize joystick support",
+                e.to_string(),
+            )
+            .unwrap();
+
+            bail!("Failed to initialize joystick support: {e}");
+        }
+        Err(e) => {
+            log::warn!("joystick support is unavailable, gamepad buttons won't work: {e}");
+            None
+        }
+    };
 
     // Loads the audio for each alarm
-    for alarm in alarms.values() {
+    for alarm in &config.alarms {
         if let Err(e) = audio.preload_file(&alarm.audio_path) {
             let message = format!("Failed to load {}\n{e}", &alarm.audio_path);
 
@@ -89,266 +117,96 @@ pub fn run() -> Result<()> {
         }
     }
 
-    // Generates a Vec<Vec<Dial>> that represents rows of dials, from the configuration
-    let dial_rows: Vec<_> =
-        // Loop through each row
-        config.dial_rows.iter()
-        .map(|row| {
-                // Loop through each dial in the row
-                row.dials.iter()
-                .map(|dial| {
-                    Dial::new(
-                        dial.name.clone(),
-                        DialRange::new(dial.range_start, dial.range_end),
-                        dial.speed
-                    )
-                })
-                .collect()
-        })
-        .collect();
-
-    {
-        let mut state = STATE.lock().unwrap();
-
-        if let AppState::Running(state) = &mut *state {
-            // Assign all of the values that we have created from the configuration file
-            // because these had to come with defaults since it is static
-            state.input_mode = config.input_mode;
-            state.trials = config.trials;
-            state.alarms = alarms;
-            state.dial_rows = dial_rows;
-            state.ball = Ball::new(
-                config.ball.random_direction_change_time_min,
-                config.ball.random_direction_change_time_max,
-                config.ball.ball_velocity,
-            );
-            state.session_output = SessionOutput::new(
-                config
-                    .output_data_path
-                    .clone()
-                    .unwrap_or_else(|| String::from(DEFAULT_OUTPUT_PATH)),
-            );
-        } else {
-            panic!("App always in the running state on startup");
-        }
-    }
-
-    // Our "model" runs in a separate thread and shares state
-    thread::spawn(move || model(&STATE, audio));
-
-    let options = eframe::NativeOptions {
-        transparent: true,
-        vsync: true,
-        maximized: true,
-        initial_window_size: Some(Vec2::new(1920.0 / 2.0, 1080.0 / 2.0)),
-        ..eframe::NativeOptions::default()
-    };
-
-    // Actually creates the eframe window for our application
-    eframe::run_native(
-        "Dials App",
-        options,
-        Box::new(move |cc| Box::new(DialsApp::new(cc, &STATE))),
-    )
-    .unwrap();
+    // The UI only needs to know how input is read, everything else belongs to the model
+    let input_mode = config.input_mode;
+    let keyboard = config.keyboard.clone();
+    let mouse = config.mouse;
+    let lock_pointer = matches!(input_mode, config::InputMode::Mouse) && mouse.lock_pointer;
+    let tracking_view = TrackingView::new(
+        config.ball.axis,
+        config.tracking.appearance,
+        config.metrics.target_radius,
+    );
+
+    let model = Model::new(config, audio, gilrs);
+
+    // The model and the UI don't share any state. The model publishes snapshots of itself for the
+    // UI to draw, and the UI sends it input events.
+    let snapshots = SnapshotCell::new(model.snapshot());
+    let (input_sender, input_receiver) = mpsc::channel();
+
+    // Our "model" runs in a separate thread
+    let model_snapshots = snapshots.clone();
+    thread::spawn(move || model.run(input_receiver, model_snapshots));
+
+    // Creates the window for our application, which runs until it is closed
+    window::run("Dials App", lock_pointer, move |ctx| {
+        DialsApp::new(
+            ctx,
+            snapshots,
+            input_sender,
+            input_mode,
+            keyboard,
+            mouse,
+            tracking_view,
+        )
+    })?;
 
     Ok(())
 }
 
-/// Our program's actual internal model, as opposed to the "view" which is our UI
-fn model(state: &Mutex<AppState>, audio: AudioManager) {
-    // Make instance of the crate that takes care of the joystick inputs.
-    let mut gilrs = Gilrs::new().unwrap();
-
-    let mut last_update = Instant::now();
-
-    // The time after the last alarm was acknowledged until
-    // the "Trial Complete!" splash screen is shown.
-    const SPLASH_SCREEN_DELAY: Duration = Duration::from_secs(10);
-    // This is set to true when all of the trials have been completed
-    let mut is_done = false;
-    // The running RMSE of the distance from the ball to the crosshair
-    let mut trial_rmse = Vec::new();
-
-    // The last Instant that a trial was run, so that the time can be measured relative to
-    // trial activations.
-    let mut last_trial_time = Instant::now();
-    // The last instant that an alarm went off, used to find trial response times
-    let mut last_alarm_time = Instant::now();
-
-    // Outputs the type of device that is detected by Gilrs.
-    // If information is not recognized by library then it will output the default OS provided name
-    for (_id, gamepad) in gilrs.gamepads() {
-        log::info!(
-            "Joystick {} detected: {:?}",
-            gamepad.name(),
-            gamepad.power_info()
-        );
-    }
+/// Reads and parses the configuration file at `path` along with any files it includes, showing an
+/// error popup if that fails
+fn load_config(path: &str) -> Result<config::Config> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let message = format!(
+                "No configuration file was found at `{path}`\n\
+                 Run `dials-research init` to create a template, \
+                 or `dials-research --default-config` to run the demo session"
+            );
 
-    // Keep track of the joystick's axes
-    // This is only called if joystick is the input mode otherwise goes to keyboard state input
-    let mut joystick_input_axes = Vec2::default();
-
-    // This allows us to request state transitions inside of the loop
-    let mut new_appstate = None;
-
-    loop {
-        thread::sleep(Duration::from_millis(2));
-
-        let delta_time = last_update.elapsed().as_secs_f32();
-
-        let mut state = state.lock().unwrap();
-
-        match &mut *state {
-            AppState::Running(state) => {
-                // Update our current trial that we are running
-                if let Some(current_trial) = state.trials.first() {
-                    let dial = state
-                        .dial_rows
-                        .iter_mut()
-                        .flat_map(|r| r.iter_mut())
-                        .find(|d| d.name() == &current_trial.dial)
-                        .unwrap();
-
-                    if dial.is_wandering() {
-                        dial.reset(Some(current_trial.alarm_time));
-                    }
-
-                    if !state.alarm_active
-                        && last_trial_time.elapsed().as_secs_f32() > current_trial.alarm_time
-                    {
-                        let alarm = state.alarms.get(&current_trial.alarm).unwrap();
-
-                        state.alarm_active = true;
-                        audio.play(&current_trial.dial, &alarm.audio_path).unwrap();
-                        last_alarm_time = Instant::now();
-                    }
-                }
-
-                // Update all dials
-                for row in state.dial_rows.iter_mut() {
-                    for dial in row.iter_mut() {
-                        dial.update(delta_time);
-                    }
-                }
-
-                // Takes the event detected by the joystick being used.
-                // Events detected can be 3 types of axes:
-                //   -X
-                //   -Y
-                //   -Z
-                // The only ones we care about are X and Y.
-                // We then take the amount the joystick moves. This is already filtered by on a scale
-                // -1 to 1 where 0 is centered or not moving.
-                while let Some(Event { event, .. }) = gilrs.next_event() {
-                    if let gilrs::ev::EventType::AxisChanged(axis, amount, _) = event {
-                        match axis {
-                            gilrs::ev::Axis::LeftStickX => {
-                                joystick_input_axes[0] = amount;
-                            }
-                            gilrs::ev::Axis::LeftStickY => {
-                                joystick_input_axes[1] = amount;
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-
-                // Depending on the type of input specified in the config file it will then proceed to
-                // decide to either use the joystick axes or the keyboard.
-                // If needed more will be added, such as Mouse input.
-                let input_axes = match state.input_mode {
-                    config::InputMode::Joystick => joystick_input_axes,
-                    config::InputMode::Keyboard => state.input_axes,
-                };
-
-                state.ball.update(input_axes, delta_time);
-                trial_rmse.push(state.ball.current_rms_error());
-
-                if let Some(key) = state.pressed_key.take() {
-                    if let Some(current_trial) = state.trials.first() {
-                        if last_trial_time.elapsed().as_secs_f32() > current_trial.alarm_time {
-                            let millis = last_alarm_time.elapsed().as_millis() as u32;
-
-                            let (feedback_text, feedback_color) =
-                                if current_trial.correct_response_key == key {
-                                    (
-                                        current_trial.feedback_text_correct.as_deref(),
-                                        current_trial.feedback_color_correct.clone(),
-                                    )
-                                } else {
-                                    (
-                                        current_trial.feedback_text_incorrect.as_deref(),
-                                        current_trial.feedback_color_incorrect.clone(),
-                                    )
-                                };
-
-                            let reaction = TrialReaction::new(
-                                state.current_trial_number,
-                                millis,
-                                current_trial.correct_response_key == key,
-                                key,
-                                trial_rmse,
-                            );
-
-                            trial_rmse = Vec::new();
-
-                            //Tell the state that a key was pressed after an alarm went off.
-                            state.tracking_state.blink(feedback_text, feedback_color);
-
-                            let dial = state
-                                .dial_rows
-                                .iter_mut()
-                                .flat_map(|r| r.iter_mut())
-                                .find(|d| d.name() == &current_trial.dial)
-                                .unwrap();
-                            // Flash the dial needle
-                            dial.flash();
-
-                            audio.stop(&current_trial.dial);
-                            dial.reset(None);
-                            last_trial_time = Instant::now();
-                            state.trials.remove(0);
-                            state.current_trial_number += 1;
-                            state.session_output.add_reaction(reaction);
-                            state.alarm_active = false;
-
-                            if !is_done && state.trials.is_empty() {
-                                state.session_output.write_to_file();
-
-                                log::info!(
-                                    "wrote session output to file: {}",
-                                    state.session_output.output_path
-                                );
-                                is_done = true;
-                            }
-                        }
-                    }
-                }
-
-                //If key detected then start running time
-                state.tracking_state.update(delta_time);
-                // We have a delay before going to the end screen
-                if is_done && last_trial_time.elapsed() >= SPLASH_SCREEN_DELAY {
-                    // Change the state to Done and therefore show the splash screen
-                    new_appstate = Some(AppState::Done);
-                }
-            }
-            AppState::Done => {}
+            dialog_popup::show(
+                "Configuration Error",
+                "Configuration file not found",
+                message.clone(),
+            )
+            .unwrap();
+
+            bail!(message);
         }
+        Err(e) => {
+            dialog_popup::show(
+                "Configuration Error",
+                "Failed to read configuration file",
+                format!("{path}: {e}"),
+            )
+            .unwrap();
 
-        // If we have requested a state change, perform it
-        if let Some(new_state) = new_appstate.take() {
-            *state = new_state;
+            bail!("Failed to read configuration file `{path}`: {e}");
         }
+    };
 
-        last_update = Instant::now();
+    match config_loader::parse(Path::new(path), &contents) {
+        Ok(config) => Ok(config),
+        Err(e) => {
+            dialog_popup::show(
+                "Configuration Error",
+                "Failed to parse configuration file",
+                format!("{e:#}"),
+            )
+            .unwrap();
+
+            bail!("Failed to parse configuration file: {e:#}");
+        }
     }
 }
 
 /// Validates a config file, or exits the program with an error printed to the command line on how
 /// to fix the validation
+///
+/// This also fills in each trial's response key from its dial if the trial doesn't specify one.
 fn validate_config(config: &mut config::Config) -> Result<()> {
     let alarm_names: Vec<_> = config.alarms.iter().map(|b| &b.name).collect();
     let dial_names: Vec<_> = config
@@ -356,55 +214,433 @@ fn validate_config(config: &mut config::Config) -> Result<()> {
         .iter()
         .flat_map(|r| r.dials.iter().map(|d| &d.name))
         .collect();
+    let scripted_dial_names: Vec<_> = config
+        .dial_rows
+        .iter()
+        .flat_map(|r| r.dials.iter())
+        .filter(|d| d.trajectory.is_some())
+        .map(|d| &d.name)
+        .collect();
+
+    let mut dial_keys: HashMap<&String, ResponseInput> = HashMap::new();
+    // Every key and button that is used by a dial, so that no two dials share one
+    let mut dial_responses: Vec<(&String, ResponseInput)> = Vec::new();
+
+    for dial in config.dial_rows.iter().flat_map(|r| r.dials.iter()) {
+        if let Some(key) = dial.response_key {
+            dial_keys.insert(&dial.name, key);
+            dial_responses.push((&dial.name, key));
+        }
+
+        if let Some(button) = dial.response_button {
+            if !matches!(button, ResponseInput::GamepadButton(_)) {
+                return Err(invalid_config(format!(
+                    "Dial `{}` specifies response button `{button}`, which is not a gamepad button\nGamepad buttons are written like `gamepad:South`",
+                    dial.name
+                )));
+            }
+
+            dial_responses.push((&dial.name, button));
+        }
+    }
+
+    for dial in config.dial_rows.iter().flat_map(|r| r.dials.iter()) {
+        if let DialSpeed::Custom {
+            seconds_per_segment,
+            deviation,
+        } = dial.speed
+        {
+            if !(seconds_per_segment.is_finite() && (0.0..seconds_per_segment).contains(&deviation))
+            {
+                return Err(invalid_config(format!(
+                    "Dial `{}` has a speed of {seconds_per_segment} seconds per segment with a deviation of {deviation}, which is invalid\nThe deviation must be at least 0.0 and less than the seconds per segment",
+                    dial.name
+                )));
+            }
+        }
+
+        match dial.motion {
+            ConfigDialMotion::OrnsteinUhlenbeck {
+                reversion,
+                volatility,
+            } if !(reversion.is_finite()
+                && reversion >= 0.0
+                && volatility.is_finite()
+                && volatility >= 0.0) =>
+            {
+                return Err(invalid_config(format!(
+                    "Dial `{}` has an Ornstein-Uhlenbeck motion with a reversion of {reversion} and a volatility of {volatility}, which is invalid\nBoth must be 0.0 or more",
+                    dial.name
+                )));
+            }
+            ConfigDialMotion::Sinusoidal { period, amplitude }
+                if !(period.is_finite() && period > 0.0 && (0.0..=1.0).contains(&amplitude)) =>
+            {
+                return Err(invalid_config(format!(
+                    "Dial `{}` has a sinusoidal motion with a period of {period} and an amplitude of {amplitude}, which is invalid\nThe period must be more than 0.0, and the amplitude from 0.0 to 1.0",
+                    dial.name
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    // When each scripted dial's script leaves its range, and the script's file name
+    let mut scripted_exits: HashMap<&String, (&String, VecDeque<f32>)> = HashMap::new();
+
+    for dial in config.dial_rows.iter().flat_map(|r| r.dials.iter()) {
+        let (Some(script), Some(trajectory)) = (&dial.script, &dial.trajectory) else {
+            continue;
+        };
+
+        if let Err(problem) = trajectory.check() {
+            return Err(invalid_config(format!(
+                "Dial `{}` has an invalid script `{script}`\n{problem}",
+                dial.name
+            )));
+        }
+
+        let exits = trajectory.range_exits(&DialRange::new(dial.range_start, dial.range_end));
+
+        log::info!(
+            "scripted dial `{}` leaves its range at {exits:.3?} seconds",
+            dial.name
+        );
+
+        scripted_exits.insert(&dial.name, (script, exits.into()));
+    }
+
+    if let BallVelocity::Custom(velocity) = config.ball.ball_velocity {
+        if !(velocity.is_finite() && velocity >= 0.0) {
+            return Err(invalid_config(format!(
+                "The ball velocity is {velocity}, which is invalid\nIt must be 0.0 or more"
+            )));
+        }
+    }
+
+    if let Some(gain) = config.ball.control_gain {
+        if !(gain.is_finite() && gain >= 0.0) {
+            return Err(invalid_config(format!(
+                "The ball's control gain is {gain}, which is invalid\nIt must be 0.0 or more"
+            )));
+        }
+    }
+
+    if config.mouse.lock_pointer && !matches!(config.input_mode, config::InputMode::Mouse) {
+        log::warn!("the mouse pointer is only locked to the window in mouse input mode");
+    }
+
+    if matches!(config.input_mode, config::InputMode::Mouse)
+        && config.mouse.control == config::MouseControl::Position
+        && config.tracking.mode == TrackingMode::Compensatory
+    {
+        log::warn!(
+            "the ball is placed at the mouse pointer, so in compensatory tracking the disturbance can't move it away from the crosshair, pursuit tracking is usually wanted instead"
+        );
+    }
+
+    if let ConfigDisturbance::SumOfSines {
+        frequencies,
+        amplitudes,
+    } = &config.ball.disturbance
+    {
+        if frequencies.is_empty() || frequencies.len() != amplitudes.len() {
+            return Err(invalid_config(String::from(
+                "The ball's sum of sines disturbance must have at least one frequency, and the same number of amplitudes as frequencies",
+            )));
+        }
+
+        let valid = |v: &f32| v.is_finite() && *v >= 0.0;
+        if !(frequencies.iter().all(valid) && amplitudes.iter().all(valid)) {
+            return Err(invalid_config(String::from(
+                "The ball's sum of sines frequencies and amplitudes must be 0.0 or more",
+            )));
+        }
+    }
+
+    if let ConfigDisturbance::Scripted { waypoints, .. } = &config.ball.disturbance {
+        if waypoints.is_empty() {
+            return Err(invalid_config(String::from(
+                "The ball's scripted disturbance must have at least one waypoint",
+            )));
+        }
+
+        let mut last_time = 0.0;
+        for (i, &[time, x, y]) in waypoints.iter().enumerate() {
+            if !(time.is_finite() && time >= last_time) {
+                return Err(invalid_config(format!(
+                    "Waypoint #{} of the ball's scripted disturbance is at {time} seconds, which is invalid\nWaypoint times must be 0.0 or more, and in order",
+                    i + 1
+                )));
+            }
+
+            if !((-1.0..=1.0).contains(&x) && (-1.0..=1.0).contains(&y)) {
+                return Err(invalid_config(format!(
+                    "Waypoint #{} of the ball's scripted disturbance is at ({x}, {y}), which is outside of the tracking frame\nPositions must be from -1.0 to 1.0",
+                    i + 1
+                )));
+            }
+
+            last_time = time;
+        }
+    }
+
+    for (i, (name, response)) in dial_responses.iter().enumerate() {
+        if let Some((other, _)) = dial_responses[..i].iter().find(|(_, r)| r == response) {
+            return Err(invalid_config(format!(
+                "Dials `{other}` and `{name}` both use the response `{response}`\nEach dial needs its own key and button"
+            )));
+        }
+    }
+
+    let keyboard = &config.keyboard;
+    let tracking_keys = keyboard.tracking_keys();
+    for (i, key) in tracking_keys.iter().enumerate() {
+        if !matches!(key, ResponseInput::Key(_)) {
+            return Err(invalid_config(format!(
+                "The keyboard uses `{key}` to move the ball, which is not a keyboard key"
+            )));
+        }
+
+        if tracking_keys[..i].contains(key) {
+            return Err(invalid_config(format!(
+                "The keyboard uses `{key}` to move the ball in more than one direction"
+            )));
+        }
+    }
+
+    if !(keyboard.ramp_up_time >= 0.0 && keyboard.ramp_down_time >= 0.0) {
+        return Err(invalid_config(String::from(
+            "The keyboard ramp times must be 0.0 or more seconds",
+        )));
+    }
+
+    let timing = &config.timing;
+    if !(timing.tick_rate > 0.0 && timing.tick_rate <= 10_000.0) {
+        return Err(invalid_config(format!(
+            "The tick rate is {}, which is invalid\nIt must be more than 0.0 and at most 10000.0 ticks per second",
+            timing.tick_rate
+        )));
+    }
+
+    if !(0.0..).contains(&timing.late_tick_threshold) {
+        return Err(invalid_config(String::from(
+            "The late tick threshold must be 0.0 or more milliseconds",
+        )));
+    }
+
+    let metrics = &config.metrics;
+    if !(metrics.target_radius > 0.0 && metrics.target_radius.is_finite()) {
+        return Err(invalid_config(format!(
+            "The metrics target radius is {}, which is invalid\nIt must be more than 0.0, as it is the size of the ring drawn around the target",
+            metrics.target_radius
+        )));
+    }
+
+    if !(0.0..).contains(&metrics.post_response_window) {
+        return Err(invalid_config(String::from(
+            "The metrics post-response window must be 0.0 or more",
+        )));
+    }
+
+    let appearance = &config.tracking.appearance;
+    if !(appearance.ball_radius > 0.0 && appearance.ball_radius < 0.5) {
+        return Err(invalid_config(format!(
+            "The ball radius is {}, which is invalid\nIt must be more than 0.0 and less than 0.5",
+            appearance.ball_radius
+        )));
+    }
+
+    let sizes = [
+        appearance.crosshair_size,
+        appearance.crosshair_width,
+        appearance.target_ring_width,
+        appearance.frame_width,
+    ];
+    if !sizes.iter().all(|size| (0.0..).contains(size)) {
+        return Err(invalid_config(String::from(
+            "The tracking appearance's sizes and widths must be 0.0 or more",
+        )));
+    }
+
+    let adaptive = &config.adaptive;
+    if !(adaptive.min_level > 0.0
+        && adaptive.min_level <= adaptive.start_level
+        && adaptive.start_level <= adaptive.max_level
+        && adaptive.max_level.is_finite())
+    {
+        return Err(invalid_config(format!(
+            "The adaptive levels are invalid\nThe min_level ({}) must be more than 0.0, and no more than the start_level ({}), which must be no more than the max_level ({})",
+            adaptive.min_level, adaptive.start_level, adaptive.max_level
+        )));
+    }
+
+    if !(adaptive.window > 0.0 && adaptive.update_interval > 0.0) {
+        return Err(invalid_config(String::from(
+            "The adaptive window and update interval must be more than 0.0 seconds",
+        )));
+    }
+
+    if !(0.0..).contains(&adaptive.target_error)
+        || !(0.0..).contains(&adaptive.step_up)
+        || !(0.0..).contains(&adaptive.step_down)
+    {
+        return Err(invalid_config(String::from(
+            "The adaptive target error and steps must be 0.0 or more",
+        )));
+    }
+
+    let joystick = &config.joystick;
+    if !(0.0..1.0).contains(&joystick.deadzone) {
+        return Err(invalid_config(format!(
+            "The joystick deadzone is {}, which is invalid\nIt must be at least 0.0 and less than 1.0",
+            joystick.deadzone
+        )));
+    }
+
+    if !(joystick.x_gain.is_finite() && joystick.y_gain.is_finite()) {
+        return Err(invalid_config(String::from(
+            "The joystick gains must be numbers",
+        )));
+    }
+
     // Loops through each trial and checks if its corresponding alarm exists in the map
-    for (trial_num, trial) in config.trials.iter().enumerate() {
+    for (trial_num, trial) in config.trials.iter_mut().enumerate() {
         let alarm_name = &trial.alarm;
         if !alarm_names.contains(&alarm_name) {
-            let message =
-                format!("Alarm `{alarm_name}` is missing!\nAvailable alarms are: {alarm_names:?}");
-
-            dialog_popup::show(
-                "Configuration Error",
-                "Invalid configuration",
-                message.clone(),
-            )
-            .unwrap();
-
-            bail!(message);
+            return Err(invalid_config(format!(
+                "Alarm `{alarm_name}` is missing!\nAvailable alarms are: {alarm_names:?}"
+            )));
         }
 
         let dial_name = &trial.dial;
 
         if !dial_names.contains(&dial_name) {
-            let message =
-                format!("Dial `{dial_name}` is missing!\nAvailable dials are: {dial_names:?}");
-
-            dialog_popup::show(
-                "Configuration Error",
-                "Invalid configuration",
-                message.clone(),
-            )
-            .unwrap();
+            return Err(invalid_config(format!(
+                "Dial `{dial_name}` is missing!\nAvailable dials are: {dial_names:?}"
+            )));
+        }
 
-            bail!(message);
+        match trial.alarm_time {
+            Some(_) if scripted_dial_names.contains(&dial_name) => {
+                log::warn!(
+                    "trial #{trial_num} is on scripted dial `{dial_name}`, so its alarm time is ignored"
+                );
+            }
+            Some(alarm_time) if !(alarm_time.is_finite() && alarm_time >= 0.0) => {
+                return Err(invalid_config(format!(
+                    "Trial #{trial_num} has an alarm time of {alarm_time}, which is invalid\nIt must be 0.0 or more"
+                )));
+            }
+            None if !scripted_dial_names.contains(&dial_name) => {
+                return Err(invalid_config(format!(
+                    "Trial #{trial_num} has no `alarm_time`\nOnly trials on a dial with a `script` can leave it out"
+                )));
+            }
+            _ => {}
         }
 
-        if !trial.correct_response_key.is_alphanumeric() {
-            let message = format!(
-                "Trial #{} specifies response key `{}`, which is invalid\nAvailable keys are: A-Z and 0-9",
-                trial_num, trial.correct_response_key
+        if trial.out_of_range.is_some() && scripted_dial_names.contains(&dial_name) {
+            log::warn!(
+                "trial #{trial_num} is on scripted dial `{dial_name}`, which keeps following its script, so its out_of_range is ignored"
             );
+            trial.out_of_range = None;
+        }
 
-            dialog_popup::show(
-                "Configuration Error",
-                "Invalid configuration",
-                message.clone(),
-            )
-            .unwrap();
+        match (trial.out_of_range, trial.recover_after) {
+            (Some(OutOfRange::Recover), None) => {
+                return Err(invalid_config(format!(
+                    "Trial #{trial_num} recovers out of range, but has no `recover_after`\nGive it the number of seconds after the alarm that the needle returns on its own"
+                )));
+            }
+            (Some(OutOfRange::Recover), Some(recover_after))
+                if !(recover_after.is_finite() && recover_after >= 0.0) =>
+            {
+                return Err(invalid_config(format!(
+                    "Trial #{trial_num} has a recover_after of {recover_after}, which is invalid\nIt must be 0.0 or more"
+                )));
+            }
+            (Some(OutOfRange::Recover), _) | (_, None) => {}
+            (_, Some(_)) => {
+                log::warn!(
+                    "trial #{trial_num} has a recover_after, but only trials with out_of_range = \"recover\" use it"
+                );
+            }
+        }
 
-            bail!(message);
+        let key = match trial
+            .correct_response_key
+            .or(dial_keys.get(dial_name).copied())
+        {
+            Some(key) => key,
+            None => {
+                return Err(invalid_config(format!(
+                    "Trial #{trial_num} has no response key\nGive the trial a `correct_response_key`, or its dial `{dial_name}` a `response_key`"
+                )));
+            }
+        };
+
+        // The tracking keys move the ball when using the keyboard
+        if keyboard.is_tracking_key(key) && matches!(config.input_mode, config::InputMode::Keyboard)
+        {
+            return Err(invalid_config(format!(
+                "Trial #{trial_num} specifies response key `{key}`, which is invalid\nIt is used to move the ball in keyboard input mode"
+            )));
+        }
+
+        trial.correct_response_key = Some(key);
+    }
+
+    // Each trial on a scripted dial sounds its alarm the first time the script leaves the range
+    // after the trial starts. A trial can't start before the alarm of the trial before it, even if
+    // every response is instant, so the script has to leave the range again after that.
+    let mut earliest_start = 0.0;
+
+    for (trial_num, trial) in config.trials.iter().enumerate() {
+        let Some((script, exits)) = scripted_exits.get_mut(&trial.dial) else {
+            earliest_start += trial.alarm_time.unwrap_or_default();
+            continue;
+        };
+
+        loop {
+            let Some(exit) = exits.pop_front() else {
+                return Err(invalid_config(format!(
+                    "Trial #{trial_num} is on scripted dial `{}`, but its script `{script}` doesn't leave the range after {earliest_start:.3} seconds, which is the earliest that the trial can start\nEach trial on a scripted dial needs the script to leave the range after the alarm of the trial before it",
+                    trial.dial
+                )));
+            };
+
+            if exit >= earliest_start {
+                earliest_start = exit;
+                break;
+            }
+
+            log::warn!(
+                "scripted dial `{}` leaves its range at {exit:.3} seconds, before trial #{trial_num} on it can start, so it won't sound an alarm",
+                trial.dial
+            );
+        }
+    }
+
+    for (dial, (_, exits)) in &scripted_exits {
+        if exits.is_empty() {
+            log::warn!(
+                "scripted dial `{dial}` has no spare range exits, so if a slow response means a trial on it starts after its exit, that trial's alarm won't sound"
+            );
         }
     }
 
     Ok(())
 }
+
+/// Shows a popup for an invalid configuration, and returns the error to exit the program with
+fn invalid_config(message: String) -> anyhow::Error {
+    dialog_popup::show(
+        "Configuration Error",
+        "Invalid configuration",
+        message.clone(),
+    )
+    .unwrap();
+
+    anyhow::anyhow!(message)
+}
@@ -1,10 +1,23 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+use dials_research::cli::{Command, USAGE};
+
 fn main() -> anyhow::Result<()> {
     env_logger::builder()
         .format_timestamp_micros()
         .filter(Some("dials-research"), log::LevelFilter::Debug)
         .filter(None, log::LevelFilter::Info)
         .init();
-    dials_research::run()
+
+    match Command::from_args(std::env::args().skip(1))? {
+        Command::Run {
+            config_path,
+            use_default_config,
+        } => dials_research::run(&config_path, use_default_config),
+        Command::Init { path, force } => dials_research::init(&path, force),
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,183 @@
+use eframe::emath::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Settings for the tracking error metrics that are written to the output
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ConfigMetrics {
+    /// The ball is on target while it is within this distance of the crosshair, where 1.0 is half
+    /// the width of the tracking frame
+    pub target_radius: f32,
+
+    /// How many seconds after each response the post-response window lasts
+    pub post_response_window: f32,
+}
+
+impl Default for ConfigMetrics {
+    fn default() -> Self {
+        Self {
+            target_radius: 0.1,
+            post_response_window: 2.0,
+        }
+    }
+}
+
+/// Accumulates how far the ball was from the crosshair over a window of time
+///
+/// Every sample is weighted by how long it lasted, so the results don't depend on how often the
+/// ball was sampled.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TrackingMetrics {
+    /// The length of the window in seconds
+    duration: f32,
+    /// The time integral of the squared distance from the crosshair
+    squared_error: f32,
+    /// The time integral of the distance from the crosshair
+    absolute_error: f32,
+    /// The time integrals of the squared horizontal and vertical distances from the crosshair
+    squared_error_x: f32,
+    squared_error_y: f32,
+    /// Seconds the ball spent within the target radius
+    on_target: f32,
+}
+
+impl TrackingMetrics {
+    /// Adds a sample of the ball's offset from the crosshair, which lasted `delta_time` seconds
+    pub fn add(&mut self, error: Vec2, delta_time: f32, target_radius: f32) {
+        let distance = error.length();
+
+        self.duration += delta_time;
+        self.squared_error += distance * distance * delta_time;
+        self.absolute_error += distance * delta_time;
+        self.squared_error_x += error.x * error.x * delta_time;
+        self.squared_error_y += error.y * error.y * delta_time;
+
+        if distance <= target_radius {
+            self.on_target += delta_time;
+        }
+    }
+
+    /// The length of the window in seconds
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Divides a time integral by the length of the window, or [`None`] if the window is empty
+    fn mean(&self, integral: f32) -> Option<f32> {
+        (self.duration > 0.0).then(|| integral / self.duration)
+    }
+
+    /// The root-mean-square distance from the crosshair
+    pub fn rmse(&self) -> Option<f32> {
+        self.mean(self.squared_error).map(f32::sqrt)
+    }
+
+    /// The mean distance from the crosshair
+    pub fn mae(&self) -> Option<f32> {
+        self.mean(self.absolute_error)
+    }
+
+    /// The fraction of the window, from 0.0 to 1.0, that the ball spent on target
+    pub fn time_on_target(&self) -> Option<f32> {
+        self.mean(self.on_target)
+    }
+
+    /// The root-mean-square horizontal distance from the crosshair
+    pub fn rmse_x(&self) -> Option<f32> {
+        self.mean(self.squared_error_x).map(f32::sqrt)
+    }
+
+    /// The root-mean-square vertical distance from the crosshair
+    pub fn rmse_y(&self) -> Option<f32> {
+        self.mean(self.squared_error_y).map(f32::sqrt)
+    }
+}
+
+/// The tracking metrics of one trial, split into windows around its alarm
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TrialMetrics {
+    /// From the start of the trial until the alarm went off
+    pub pre_alarm: TrackingMetrics,
+    /// From when the alarm went off until it was responded to
+    pub during_alarm: TrackingMetrics,
+    /// A fixed length of time after the response
+    pub post_response: TrackingMetrics,
+}
+
+impl TrialMetrics {
+    /// The names of each window, in the order of [`TrialMetrics::windows`]
+    pub const WINDOW_NAMES: [&'static str; 3] = ["pre_alarm", "during_alarm", "post_response"];
+
+    /// Each window, in the order of [`TrialMetrics::WINDOW_NAMES`]
+    pub fn windows(&self) -> [&TrackingMetrics; 3] {
+        [&self.pre_alarm, &self.during_alarm, &self.post_response]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_RADIUS: f32 = 0.1;
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("the window shouldn't be empty");
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{actual} isn't {expected}"
+        );
+    }
+
+    #[test]
+    fn empty_windows_have_no_metrics() {
+        let metrics = TrackingMetrics::default();
+
+        assert_eq!(metrics.duration(), 0.0);
+        assert_eq!(metrics.rmse(), None);
+        assert_eq!(metrics.mae(), None);
+        assert_eq!(metrics.time_on_target(), None);
+        assert_eq!(metrics.rmse_x(), None);
+        assert_eq!(metrics.rmse_y(), None);
+    }
+
+    #[test]
+    fn samples_are_weighted_by_how_long_they_lasted() {
+        let mut metrics = TrackingMetrics::default();
+        metrics.add(Vec2::new(0.3, 0.4), 3.0, TARGET_RADIUS);
+        metrics.add(Vec2::ZERO, 1.0, TARGET_RADIUS);
+
+        assert_eq!(metrics.duration(), 4.0);
+        assert_close(metrics.mae(), 0.5 * 3.0 / 4.0);
+        assert_close(metrics.rmse(), (0.25_f32 * 3.0 / 4.0).sqrt());
+        assert_close(metrics.rmse_x(), (0.09_f32 * 3.0 / 4.0).sqrt());
+        assert_close(metrics.rmse_y(), (0.16_f32 * 3.0 / 4.0).sqrt());
+    }
+
+    #[test]
+    fn metrics_dont_depend_on_the_sampling_rate() {
+        let mut coarse = TrackingMetrics::default();
+        let mut fine = TrackingMetrics::default();
+        for error in [Vec2::new(0.2, 0.0), Vec2::new(0.0, -0.6)] {
+            coarse.add(error, 0.5, TARGET_RADIUS);
+            for _ in 0..50 {
+                fine.add(error, 0.01, TARGET_RADIUS);
+            }
+        }
+
+        assert_close(fine.rmse(), coarse.rmse().unwrap());
+        assert_close(fine.mae(), coarse.mae().unwrap());
+        assert_close(fine.rmse_x(), coarse.rmse_x().unwrap());
+        assert_close(fine.rmse_y(), coarse.rmse_y().unwrap());
+    }
+
+    #[test]
+    fn time_on_target_is_the_fraction_within_the_target_radius() {
+        let mut metrics = TrackingMetrics::default();
+        metrics.add(Vec2::new(0.05, 0.0), 1.0, TARGET_RADIUS);
+        // On the edge of the target still counts
+        metrics.add(Vec2::new(0.0, TARGET_RADIUS), 0.5, TARGET_RADIUS);
+        metrics.add(Vec2::new(0.1, 0.1), 2.5, TARGET_RADIUS);
+
+        assert_close(metrics.time_on_target(), 1.5 / 4.0);
+    }
+}
@@ -0,0 +1,913 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{mpsc::Receiver, Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use eframe::{
+    emath::{Pos2, Vec2},
+    epaint::Color32,
+};
+use gilrs::{ev::EventType, Event, GamepadId, Gilrs};
+
+use crate::{
+    adaptive::AdaptiveDifficulty,
+    audio::AudioManager,
+    ball::Ball,
+    config::{Config, ConfigAlarm, ConfigTrial, InputMode, MouseControl},
+    dial::{Dial, DialRange, OutOfRange, ScriptedAlarms},
+    joystick::{ConfigJoystick, DeviceEvent, DeviceEventKind},
+    keyboard::ConfigKeyboard,
+    metrics::{ConfigMetrics, TrackingMetrics, TrialMetrics},
+    output::{SessionOutput, TrialReaction},
+    response::{ResponseEvent, ResponseInput},
+    timing::{ConfigTiming, TickStats},
+    tracking_widget::TrackingWidgetState,
+    DEFAULT_OUTPUT_PATH,
+};
+
+/// The time after the last alarm was acknowledged until the "Trial Complete!" splash screen is
+/// shown
+const SPLASH_SCREEN_DELAY: Duration = Duration::from_secs(10);
+
+/// If the model falls further behind than this, the rest of the missed time is skipped instead of
+/// being caught up, so that the dials and ball don't jump
+const MAX_CATCH_UP_TIME: Duration = Duration::from_millis(250);
+
+/// How fast the participant brings a needle back into range for [`OutOfRange::Correct`], in values
+/// per second
+const CORRECTION_SPEED: f32 = 500.0;
+
+/// How often the tick statistics are written to the log
+const TICK_STATS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Input from the UI that is sent to the model
+#[derive(Debug, Clone, Copy)]
+pub enum InputEvent {
+    /// The keys that move the ball changed, as input axes: [-1.0 to 1.0, -1.0 to 1.0]
+    KeyboardAxes(Vec2),
+    /// The mouse pointer's offset from the center of the tracking frame, clamped to the frame and
+    /// stored as input axes: [-1.0 to 1.0, -1.0 to 1.0]
+    MouseAxes(Vec2),
+    /// A key was pressed, which might respond to an alarm
+    Response(ResponseEvent),
+    /// A key that was pressed as a response was released
+    ResponseReleased(ResponseInput),
+}
+
+/// What a dial looks like at one point in time
+#[derive(Debug, Clone)]
+pub struct DialSnapshot {
+    pub value: f32,
+    pub in_range: DialRange,
+    pub flashing: bool,
+    pub label: Option<String>,
+}
+
+/// Everything the UI needs to draw the model at one point in time
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub dial_rows: Vec<Vec<DialSnapshot>>,
+    pub ball_pos: Pos2,
+    /// Where the target is, in pursuit tracking
+    pub target_pos: Option<Pos2>,
+    pub key_detected: bool,
+    pub feedback_text: Option<String>,
+    pub outline_color: Color32,
+    /// Set while the session is paused, with the reason that is shown to the participant
+    pub paused: Option<String>,
+    /// Set once every trial is complete and the splash screen should be shown
+    pub done: bool,
+}
+
+/// Holds the latest [`Snapshot`] published by the model, for the UI to draw
+///
+/// The lock is only held to swap or clone the pointer to a snapshot, so the model and the UI never
+/// wait on each other's work.
+#[derive(Clone)]
+pub struct SnapshotCell(Arc<Mutex<Arc<Snapshot>>>);
+
+impl SnapshotCell {
+    pub fn new(snapshot: Snapshot) -> Self {
+        Self(Arc::new(Mutex::new(Arc::new(snapshot))))
+    }
+
+    /// Replaces the latest snapshot
+    pub fn publish(&self, snapshot: Snapshot) {
+        let snapshot = Arc::new(snapshot);
+        let old = std::mem::replace(&mut *self.0.lock().unwrap(), snapshot);
+
+        // The old snapshot is freed here, after the lock is released, if the UI is done with it
+        drop(old);
+    }
+
+    /// Returns the latest snapshot
+    pub fn latest(&self) -> Arc<Snapshot> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// The tracking error after a response, which is stored with the response's reaction once it has
+/// lasted long enough
+struct PostResponseWindow {
+    /// The index of the reaction in the session output
+    reaction: usize,
+    metrics: TrackingMetrics,
+}
+
+/// Our program's actual internal model, as opposed to the "view" which is our UI
+///
+/// The model owns all of the session's state. It is driven by [`InputEvent`]s and [`Model::update`],
+/// and describes itself through [`Snapshot`]s, so it doesn't depend on the UI.
+pub struct Model {
+    audio: AudioManager,
+    /// [`None`] if gamepads aren't supported, which is only allowed when the joystick isn't used to
+    /// move the ball
+    gilrs: Option<Gilrs>,
+    joystick: ConfigJoystick,
+    keyboard: ConfigKeyboard,
+    input_mode: InputMode,
+    mouse_control: MouseControl,
+    timing: ConfigTiming,
+    tick_stats: TickStats,
+    metrics: ConfigMetrics,
+    adaptive: AdaptiveDifficulty,
+
+    dial_rows: Vec<Vec<Dial>>,
+    /// When the alarms on scripted dials are due, in sim time
+    scripted_alarms: ScriptedAlarms,
+    trials: Vec<ConfigTrial>,
+    alarms: HashMap<String, ConfigAlarm>,
+    /// Maps dial names to the gamepad button that can also respond to their alarms
+    dial_buttons: HashMap<String, ResponseInput>,
+    ball: Ball,
+    alarm_active: bool,
+    current_trial_number: usize,
+    session_output: SessionOutput,
+    tracking_state: TrackingWidgetState,
+
+    /// Keys and buttons that have been pressed but not yet handled, oldest first, which are
+    /// interpreted as alarm reactions
+    responses: VecDeque<ResponseEvent>,
+    /// Keys and buttons that are being held down
+    held_responses: HashSet<ResponseInput>,
+    /// The response and reaction time to the current trial, while its needle is being brought
+    /// back into range
+    correction: Option<(ResponseInput, u32)>,
+    /// The axes of the tracking keys being held
+    keyboard_target_axes: Vec2,
+    /// The keyboard's input axes, which ramp towards the keys being held
+    keyboard_axes: Vec2,
+    mouse_axes: Vec2,
+    /// The raw axes of the selected joystick
+    joystick_axes: Vec2,
+
+    session_start: Instant,
+    /// How long the session has been simulated for, in seconds, not counting pauses
+    ///
+    /// Trials are timed with this rather than the wall clock, so that alarms stay in step with the
+    /// dials even when ticks are caught up or skipped.
+    sim_time: f64,
+    /// The instant that the current tick was due, which sim times are mapped to for reaction
+    /// times
+    tick_instant: Instant,
+    /// The sim time that the current trial's needle started drifting out, or that the last trial
+    /// ended while the next one hasn't started
+    last_trial_time: f64,
+    /// The sim time of the tick that the last alarm went off on
+    last_alarm_time: f64,
+    /// The instant of the tick that the last alarm went off on, which reaction times are measured
+    /// from
+    last_alarm_instant: Instant,
+    /// The squared distance from the ball to the crosshair at every tick of the current trial
+    squared_distances: Vec<f32>,
+    /// The difficulty level at every tick of the current trial, if it is adaptive
+    levels: Vec<f32>,
+    /// The tracking error of the current trial so far
+    trial_metrics: TrialMetrics,
+    /// The tracking error after recent responses
+    post_response_windows: Vec<PostResponseWindow>,
+    /// This is set to true when all of the trials have been completed
+    is_done: bool,
+    /// Set once the output has been written, after the last post-response window
+    output_written: bool,
+    /// Set once the splash screen should be shown
+    show_splash_screen: bool,
+
+    /// The gamepad whose events are used, only one is used at a time so that a second controller
+    /// can't interfere with the session
+    selected_gamepad: Option<GamepadId>,
+    /// When the session was paused, if it is paused
+    paused_since: Option<Instant>,
+    /// The reason the session is paused, shown to the participant
+    paused: Option<String>,
+}
+
+impl Model {
+    /// Creates the model for a session from a validated configuration
+    pub fn new(config: Config, audio: AudioManager, gilrs: Option<Gilrs>) -> Self {
+        // Maps alarm names to alarm structs
+        let alarms = config
+            .alarms
+            .into_iter()
+            .map(|d| (d.name.clone(), d))
+            .collect();
+
+        let dial_buttons = config
+            .dial_rows
+            .iter()
+            .flat_map(|r| r.dials.iter())
+            .filter_map(|d| d.response_button.map(|b| (d.name.clone(), b)))
+            .collect();
+
+        let mut scripted_alarms = ScriptedAlarms::default();
+
+        for dial in config.dial_rows.iter().flat_map(|r| r.dials.iter()) {
+            if let Some(script) = &dial.trajectory {
+                let range = DialRange::new(dial.range_start, dial.range_end);
+                scripted_alarms.add(dial.name.clone(), script, &range);
+            }
+        }
+
+        // Generates a Vec<Vec<Dial>> that represents rows of dials, from the configuration
+        let dial_rows =
+            // Loop through each row
+            config.dial_rows.iter()
+            .map(|row| {
+                    // Loop through each dial in the row
+                    row.dials.iter()
+                    .map(|dial| {
+                        Dial::new(
+                            dial.name.clone(),
+                            DialRange::new(dial.range_start, dial.range_end),
+                            dial.motion.motion(dial.speed, rand::random()),
+                            dial.label.clone(),
+                            dial.trajectory.as_ref(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut session_output = SessionOutput::new(
+            config
+                .output_data_path
+                .unwrap_or_else(|| String::from(DEFAULT_OUTPUT_PATH)),
+        );
+        session_output.metadata.input_mode = config.input_mode;
+
+        let mut ball = Ball::new(&config.ball, &config.tracking);
+        let adaptive = AdaptiveDifficulty::new(config.adaptive);
+
+        if adaptive.is_enabled() {
+            let (speed_scale, direction_change_scale) = adaptive.scales();
+            ball.set_difficulty(speed_scale, direction_change_scale);
+        }
+
+        let frame_color = config.tracking.appearance.frame_color.0;
+        let now = Instant::now();
+
+        let mut model = Self {
+            audio,
+            gilrs,
+            joystick: config.joystick,
+            keyboard: config.keyboard,
+            input_mode: config.input_mode,
+            mouse_control: config.mouse.control,
+            timing: config.timing,
+            tick_stats: TickStats::default(),
+            metrics: config.metrics,
+            adaptive,
+            dial_rows,
+            scripted_alarms,
+            trials: config.trials,
+            alarms,
+            dial_buttons,
+            ball,
+            alarm_active: false,
+            current_trial_number: 1,
+            session_output,
+            tracking_state: TrackingWidgetState::new(false, None, 0.0, frame_color, frame_color),
+            responses: VecDeque::new(),
+            held_responses: HashSet::new(),
+            correction: None,
+            keyboard_target_axes: Vec2::ZERO,
+            keyboard_axes: Vec2::ZERO,
+            mouse_axes: Vec2::ZERO,
+            joystick_axes: Vec2::ZERO,
+            session_start: now,
+            sim_time: 0.0,
+            tick_instant: now,
+            last_trial_time: 0.0,
+            last_alarm_time: 0.0,
+            last_alarm_instant: now,
+            squared_distances: Vec::new(),
+            levels: Vec::new(),
+            trial_metrics: TrialMetrics::default(),
+            post_response_windows: Vec::new(),
+            is_done: false,
+            output_written: false,
+            show_splash_screen: false,
+            selected_gamepad: None,
+            paused_since: None,
+            paused: None,
+        };
+
+        model.select_gamepad();
+
+        model
+    }
+
+    /// Runs the model forever, handling input events from `inputs` and publishing a snapshot to
+    /// `snapshots` after every update
+    ///
+    /// The model is always advanced in whole ticks of the same length, no matter how long the
+    /// thread actually sleeps for, so that timing jitter doesn't change how the dials and ball
+    /// move. Time the thread oversleeps is made up for with extra ticks.
+    pub fn run(mut self, inputs: Receiver<InputEvent>, snapshots: SnapshotCell) {
+        let tick = self.timing.tick_duration();
+        let delta_time = tick.as_secs_f32();
+
+        // Time that has passed but hasn't been simulated yet
+        let mut accumulator = Duration::ZERO;
+        let mut last_time = Instant::now();
+        let mut last_log = Instant::now();
+
+        loop {
+            thread::sleep(tick.saturating_sub(accumulator));
+
+            for event in inputs.try_iter() {
+                self.handle_input(event);
+            }
+
+            let now = Instant::now();
+            accumulator += now - last_time;
+            last_time = now;
+
+            if accumulator > MAX_CATCH_UP_TIME {
+                let dropped = accumulator - MAX_CATCH_UP_TIME;
+                log::warn!("the model fell {dropped:?} behind, skipping ahead");
+
+                self.tick_stats.drop_time(dropped);
+                accumulator = MAX_CATCH_UP_TIME;
+            }
+
+            let mut ticked = false;
+
+            while accumulator >= tick {
+                // Each tick was due once a tick's worth of time had built up
+                self.tick_stats.record(accumulator - tick, &self.timing);
+                accumulator -= tick;
+
+                self.tick_instant = now - accumulator;
+                self.update(delta_time);
+                ticked = true;
+            }
+
+            if ticked {
+                snapshots.publish(self.snapshot());
+            }
+
+            if last_log.elapsed() >= TICK_STATS_LOG_INTERVAL {
+                log::info!("tick statistics: {:?}", self.tick_stats);
+                last_log = Instant::now();
+            }
+        }
+    }
+
+    /// Takes in an input event from the UI, which is used on the next update
+    pub fn handle_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::KeyboardAxes(axes) => self.keyboard_target_axes = axes,
+            InputEvent::MouseAxes(axes) => self.mouse_axes = axes,
+            InputEvent::Response(response) => {
+                self.held_responses.insert(response.input);
+                self.responses.push_back(response);
+            }
+            InputEvent::ResponseReleased(input) => {
+                self.held_responses.remove(&input);
+            }
+        }
+    }
+
+    /// Describes the model as it is now, for the UI to draw
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            dial_rows: self
+                .dial_rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|dial| DialSnapshot {
+                            value: dial.value(),
+                            in_range: dial.in_range(),
+                            flashing: dial.is_flashing(),
+                            label: dial.label().map(String::from),
+                        })
+                        .collect()
+                })
+                .collect(),
+            ball_pos: self.ball.pos(),
+            target_pos: self.ball.target_pos(),
+            key_detected: self.tracking_state.key_detected,
+            feedback_text: self.tracking_state.feedback_text.clone(),
+            outline_color: self.tracking_state.outline_color,
+            paused: self.paused.clone(),
+            done: self.show_splash_screen,
+        }
+    }
+
+    /// Chooses the first connected gamepad that the config selects
+    fn select_gamepad(&mut self) {
+        let Some(gilrs) = &self.gilrs else {
+            return;
+        };
+
+        // Outputs the type of device that is detected by Gilrs.
+        // If information is not recognized by library then it will output the default OS provided
+        // name
+        for (id, gamepad) in gilrs.gamepads() {
+            let selected = self.selected_gamepad.is_none() && self.joystick.selects(id, &gamepad);
+
+            log::info!(
+                "Joystick {} detected with ID {id}: {:?}{}",
+                gamepad.name(),
+                gamepad.power_info(),
+                if selected {
+                    " (selected)"
+                } else {
+                    " (not selected, ignoring)"
+                }
+            );
+
+            if selected {
+                self.selected_gamepad = Some(id);
+
+                let event = DeviceEvent::new(DeviceEventKind::Selected, 0.0, id, &gamepad);
+                self.session_output.metadata.devices.push(event);
+            }
+        }
+    }
+
+    /// Advances the session by `delta_time` seconds
+    pub fn update(&mut self, delta_time: f32) {
+        if self.show_splash_screen {
+            return;
+        }
+
+        self.poll_gamepad();
+
+        if self.update_paused() {
+            // Nothing moves while paused, and responses are ignored
+            self.responses.clear();
+            return;
+        }
+
+        // Update our current trial that we are running
+        if let Some(current_trial) = self.trials.first() {
+            let dial = self
+                .dial_rows
+                .iter_mut()
+                .flat_map(|r| r.iter_mut())
+                .find(|d| d.name() == &current_trial.dial)
+                .unwrap();
+
+            // Only trials on scripted dials leave out the alarm time
+            let alarm_time = current_trial.alarm_time.unwrap_or_default();
+
+            // A needle that is coming back from the last trial gets back into range before the
+            // next trial starts, so that it can drift out on time
+            if dial.is_wandering() && !dial.is_recovering() {
+                dial.reset(Some(alarm_time));
+                self.last_trial_time = self.sim_time;
+
+                if current_trial.out_of_range == Some(OutOfRange::Drift) {
+                    dial.keep_drifting();
+                }
+            }
+
+            // The alarm waits for the trial to start, and a scripted dial's alarm sounds when its
+            // script next leaves the range after that
+            let alarm_due = if dial.is_wandering() {
+                false
+            } else if dial.is_scripted() {
+                self.scripted_alarms
+                    .next(&current_trial.dial, self.last_trial_time as f32)
+                    .is_some_and(|exit| self.sim_time >= f64::from(exit))
+            } else {
+                self.sim_time - self.last_trial_time >= f64::from(alarm_time)
+            };
+
+            if !self.alarm_active && alarm_due {
+                let alarm = self.alarms.get(&current_trial.alarm).unwrap();
+
+                self.alarm_active = true;
+                self.audio
+                    .play(&current_trial.dial, &alarm.audio_path)
+                    .unwrap();
+                self.last_alarm_time = self.sim_time;
+                self.last_alarm_instant = self.tick_instant;
+                self.scripted_alarms.sounded(&current_trial.dial);
+            }
+        }
+
+        // Update all dials
+        for row in self.dial_rows.iter_mut() {
+            for dial in row.iter_mut() {
+                dial.update(delta_time);
+            }
+        }
+
+        // Depending on the type of input specified in the config file it will then proceed to
+        // decide to either use the joystick axes, the keyboard, or the mouse.
+        let input_axes = match self.input_mode {
+            InputMode::Joystick => self.joystick.apply(self.joystick_axes),
+            InputMode::Keyboard => {
+                self.keyboard_axes =
+                    self.keyboard
+                        .ramp(self.keyboard_axes, self.keyboard_target_axes, delta_time);
+                self.keyboard_axes
+            }
+            InputMode::Mouse => match self.mouse_control {
+                MouseControl::Velocity => self.mouse_axes,
+                // The ball is placed at the pointer instead
+                MouseControl::Position => Vec2::ZERO,
+            },
+        };
+
+        self.ball.update(input_axes, delta_time);
+
+        if matches!(self.input_mode, InputMode::Mouse)
+            && self.mouse_control == MouseControl::Position
+        {
+            // Correcting for the fact that positive y is up for input axes
+            self.ball
+                .place(Vec2::new(self.mouse_axes.x, -self.mouse_axes.y));
+        }
+        self.record_error(delta_time);
+        self.adapt_difficulty(delta_time);
+
+        self.handle_responses();
+        self.update_out_of_range(delta_time);
+
+        if self.is_done && !self.output_written && self.post_response_windows.is_empty() {
+            self.write_output();
+            self.output_written = true;
+        }
+
+        //If key detected then start running time
+        self.tracking_state.update(delta_time);
+        // We have a delay before going to the end screen
+        if self.output_written
+            && self.sim_time - self.last_trial_time >= SPLASH_SCREEN_DELAY.as_secs_f64()
+        {
+            self.show_splash_screen = true;
+        }
+
+        self.sim_time += f64::from(delta_time);
+    }
+
+    /// Adds the ball's current distance from the crosshair, which lasted `delta_time` seconds, to
+    /// the tracking error of the current trial and recent responses
+    fn record_error(&mut self, delta_time: f32) {
+        let error = self.ball.error();
+        let target_radius = self.metrics.target_radius;
+
+        if !self.trials.is_empty() {
+            self.squared_distances.push(error.length_sq());
+
+            if self.adaptive.is_enabled() {
+                self.levels.push(self.adaptive.level());
+            }
+
+            let window = if self.alarm_active {
+                &mut self.trial_metrics.during_alarm
+            } else {
+                &mut self.trial_metrics.pre_alarm
+            };
+            window.add(error, delta_time, target_radius);
+        }
+
+        // Windows that have lasted long enough are stored with their reaction
+        let reactions = &mut self.session_output.trial_reactions;
+        let window_length = self.metrics.post_response_window;
+
+        self.post_response_windows.retain_mut(|window| {
+            if window.metrics.duration() >= window_length {
+                reactions[window.reaction].metrics.post_response = window.metrics;
+                false
+            } else {
+                window.metrics.add(error, delta_time, target_radius);
+                true
+            }
+        });
+    }
+
+    /// Adjusts the ball's difficulty to the participant's recent tracking error, while there are
+    /// trials left
+    fn adapt_difficulty(&mut self, delta_time: f32) {
+        if self.trials.is_empty() || !self.adaptive.is_enabled() {
+            return;
+        }
+
+        self.adaptive.add(self.ball.error().length_sq(), delta_time);
+
+        let (speed_scale, direction_change_scale) = self.adaptive.scales();
+        self.ball
+            .set_difficulty(speed_scale, direction_change_scale);
+    }
+
+    /// Takes the events from the selected gamepad
+    ///
+    /// Events detected can be 3 types of axes:
+    ///   -X
+    ///   -Y
+    ///   -Z
+    /// The only ones we care about are the two that are used for X and Y.
+    /// We then take the amount the joystick moves. This is already filtered by on a scale
+    /// -1 to 1 where 0 is centered or not moving.
+    /// Gamepad buttons can also be used to respond to alarms.
+    /// Which axes are used, and which gamepad is listened to, is set in the config.
+    fn poll_gamepad(&mut self) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        while let Some(Event { id, event, time }) = gilrs.next_event() {
+            let gamepad = gilrs.gamepad(id);
+            let event_time = self.session_start.elapsed().as_secs_f32();
+
+            match event {
+                EventType::Connected
+                    if self.selected_gamepad.is_none() && self.joystick.selects(id, &gamepad) =>
+                {
+                    log::info!(
+                        "Joystick {} connected with ID {id}, selected",
+                        gamepad.name()
+                    );
+
+                    self.selected_gamepad = Some(id);
+                    let event =
+                        DeviceEvent::new(DeviceEventKind::Connected, event_time, id, &gamepad);
+                    self.session_output.metadata.devices.push(event);
+                }
+                EventType::Disconnected if self.selected_gamepad == Some(id) => {
+                    log::warn!("Joystick {} with ID {id} was disconnected", gamepad.name());
+
+                    self.selected_gamepad = None;
+                    self.joystick_axes = Vec2::ZERO;
+                    let event =
+                        DeviceEvent::new(DeviceEventKind::Disconnected, event_time, id, &gamepad);
+                    self.session_output.metadata.devices.push(event);
+                }
+                _ if self.selected_gamepad != Some(id) => {}
+                EventType::AxisChanged(axis, amount, _) => {
+                    if axis == self.joystick.x_axis.into() {
+                        self.joystick_axes[0] = amount;
+                    } else if axis == self.joystick.y_axis.into() {
+                        self.joystick_axes[1] = amount;
+                    }
+                }
+                EventType::ButtonPressed(button, _) => {
+                    // The reaction is timed from when gilrs saw the press, rather than
+                    // from this model update
+                    let press_time = system_time_to_instant(time);
+                    let input = ResponseInput::GamepadButton(button);
+
+                    self.held_responses.insert(input);
+                    self.responses
+                        .push_back(ResponseEvent::new(input, press_time));
+                }
+                EventType::ButtonReleased(button, _) => {
+                    self.held_responses
+                        .remove(&ResponseInput::GamepadButton(button));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Pauses or resumes the session, returning true if it is paused
+    ///
+    /// The session can't continue without the joystick that moves the ball, so it is paused until
+    /// one is plugged back in.
+    fn update_paused(&mut self) -> bool {
+        let joystick_lost = !self.is_done
+            && matches!(self.input_mode, InputMode::Joystick)
+            && self.selected_gamepad.is_none();
+
+        match (joystick_lost, self.paused_since) {
+            (true, None) => {
+                log::warn!("pausing the session until a joystick is connected");
+
+                self.paused_since = Some(Instant::now());
+                self.paused = Some(String::from(
+                    "Joystick not connected\nPlug it in to continue",
+                ));
+            }
+            (false, Some(since)) => {
+                let paused_for = since.elapsed();
+                log::info!("resuming the session after {paused_for:?}");
+
+                // Time spent paused doesn't count towards reaction times, and the sim clock
+                // doesn't run while paused
+                self.last_alarm_instant += paused_for;
+                self.session_output.metadata.paused_seconds += paused_for.as_secs_f32();
+
+                self.paused_since = None;
+                self.paused = None;
+            }
+            _ => {}
+        }
+
+        self.paused_since.is_some()
+    }
+
+    /// Handles every key and button press in the order it happened, so none are lost if several
+    /// arrive between model updates
+    fn handle_responses(&mut self) {
+        while let Some(ResponseEvent {
+            input: key,
+            time: press_time,
+        }) = self.responses.pop_front()
+        {
+            let Some(current_trial) = self.trials.first() else {
+                continue;
+            };
+
+            // Presses from before the alarm went off don't count as a reaction, and neither do
+            // presses while the needle is being brought back
+            if !self.alarm_active
+                || press_time < self.last_alarm_instant
+                || self.correction.is_some()
+            {
+                continue;
+            }
+
+            let is_correct = self.is_correct_response(current_trial, key);
+            let millis = press_time
+                .duration_since(self.last_alarm_instant)
+                .as_millis() as u32;
+
+            // The trial only ends once the needle has been brought back into range
+            if is_correct && current_trial.out_of_range == Some(OutOfRange::Correct) {
+                self.correction = Some((key, millis));
+                continue;
+            }
+
+            self.finish_trial(Some((key, millis)), is_correct);
+        }
+    }
+
+    /// If `input` is the right response to `trial`: its key, or the gamepad button of its dial
+    fn is_correct_response(&self, trial: &ConfigTrial, input: ResponseInput) -> bool {
+        // Every trial has a key once the config has been validated
+        trial.correct_response_key == Some(input)
+            || self.dial_buttons.get(&trial.dial) == Some(&input)
+    }
+
+    /// Brings back the needle of the current trial while its response is held, or ends the trial
+    /// as a miss once its needle is due to return on its own
+    fn update_out_of_range(&mut self, delta_time: f32) {
+        let Some(current_trial) = self.trials.first() else {
+            return;
+        };
+
+        if !self.alarm_active {
+            return;
+        }
+
+        match current_trial.out_of_range {
+            Some(OutOfRange::Recover) => {
+                // Every recovering trial has a time once the config has been validated
+                let recover_after = current_trial.recover_after.unwrap_or_default();
+
+                if self.sim_time - self.last_alarm_time >= f64::from(recover_after) {
+                    self.finish_trial(None, false);
+                }
+            }
+            Some(OutOfRange::Correct) => {
+                let Some(response) = self.correction else {
+                    return;
+                };
+
+                let is_held = self
+                    .held_responses
+                    .iter()
+                    .any(|&input| self.is_correct_response(current_trial, input));
+
+                if !is_held {
+                    return;
+                }
+
+                let dial = self
+                    .dial_rows
+                    .iter_mut()
+                    .flat_map(|r| r.iter_mut())
+                    .find(|d| d.name() == &current_trial.dial)
+                    .unwrap();
+
+                if dial.correct(CORRECTION_SPEED * delta_time) {
+                    self.finish_trial(Some(response), true);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Ends the current trial with the key or button that responded to it and the reaction time,
+    /// or with nothing if it was missed
+    fn finish_trial(&mut self, response: Option<(ResponseInput, u32)>, is_correct: bool) {
+        let current_trial = self.trials.remove(0);
+
+        let (feedback_text, feedback_color) = if is_correct {
+            (
+                current_trial.feedback_text_correct.as_deref(),
+                current_trial.feedback_color_correct.clone(),
+            )
+        } else {
+            (
+                current_trial.feedback_text_incorrect.as_deref(),
+                current_trial.feedback_color_incorrect.clone(),
+            )
+        };
+
+        let reaction = TrialReaction::new(
+            self.current_trial_number,
+            response.map(|(_, millis)| millis),
+            is_correct,
+            response.map(|(key, _)| key),
+            std::mem::take(&mut self.squared_distances),
+            std::mem::take(&mut self.levels),
+            std::mem::take(&mut self.trial_metrics),
+        );
+
+        self.post_response_windows.push(PostResponseWindow {
+            reaction: self.session_output.trial_reactions.len(),
+            metrics: TrackingMetrics::default(),
+        });
+
+        //Tell the state that a key was pressed after an alarm went off.
+        self.tracking_state.blink(feedback_text, feedback_color);
+
+        let dial = self
+            .dial_rows
+            .iter_mut()
+            .flat_map(|r| r.iter_mut())
+            .find(|d| d.name() == &current_trial.dial)
+            .unwrap();
+
+        if response.is_some() {
+            // Flash the dial needle
+            dial.flash();
+        }
+
+        // A needle that was brought back or that returns on its own carries on from where it is
+        if self.correction.take().is_some() || response.is_none() {
+            dial.recover();
+        } else {
+            dial.reset(None);
+        }
+
+        self.audio.stop(&current_trial.dial);
+        self.last_trial_time = self.sim_time;
+        self.current_trial_number += 1;
+        self.session_output.add_reaction(reaction);
+        self.alarm_active = false;
+
+        if self.trials.is_empty() {
+            self.is_done = true;
+        }
+    }
+
+    /// Writes the session's results, along with its metadata
+    fn write_output(&mut self) {
+        log::info!("tick statistics: {:?}", self.tick_stats);
+
+        let metadata = &mut self.session_output.metadata;
+        metadata.timing = self.tick_stats.clone();
+
+        if let Some(warning) = self.tick_stats.warning(&self.timing) {
+            log::warn!("{warning}");
+            metadata.warnings.push(warning);
+        }
+
+        self.session_output.write_to_file();
+
+        log::info!(
+            "wrote session output to file: {}",
+            self.session_output.output_path
+        );
+    }
+}
+
+/// Converts the [`SystemTime`] of an input event into the [`Instant`] that it happened at
+///
+/// Events can't be from the future, so any clock difference results in the current instant.
+fn system_time_to_instant(time: SystemTime) -> Instant {
+    let now = Instant::now();
+    let age = SystemTime::now().duration_since(time).unwrap_or_default();
+
+    now.checked_sub(age).unwrap_or(now)
+}
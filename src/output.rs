@@ -1,29 +1,95 @@
 use derive_new::new;
-use std::io::Write;
+use serde::Serialize;
+
+use crate::{
+    config::InputMode,
+    joystick::DeviceEvent,
+    metrics::{TrackingMetrics, TrialMetrics},
+    response::ResponseInput,
+    timing::TickStats,
+};
+use std::{io::Write, path::Path};
 
 /// A constant for the CSV file headers
 const CSV_HEADERS: &str = "trial, response_time, correct_key, key";
 
+/// The metrics written for each window of a trial, which follow [`CSV_HEADERS`] with the window's
+/// name in front, such as `pre_alarm_rmse`
+const METRIC_NAMES: [&str; 5] = ["rmse", "mae", "time_on_target", "rmse_x", "rmse_y"];
+
+/// Returns the values of [`METRIC_NAMES`] for a window
+fn metric_values(metrics: &TrackingMetrics) -> [Option<f32>; 5] {
+    [
+        metrics.rmse(),
+        metrics.mae(),
+        metrics.time_on_target(),
+        metrics.rmse_x(),
+        metrics.rmse_y(),
+    ]
+}
+
+/// The CSV headers for every metric of every window
+fn metric_headers() -> String {
+    TrialMetrics::WINDOW_NAMES
+        .iter()
+        .flat_map(|window| METRIC_NAMES.iter().map(move |m| format!(", {window}_{m}")))
+        .collect()
+}
+
+/// The CSV columns for every metric of every window, which are left empty for empty windows
+fn metric_columns(metrics: &TrialMetrics) -> String {
+    metrics
+        .windows()
+        .iter()
+        .flat_map(|window| metric_values(window))
+        .map(|value| value.map_or(String::from(","), |v| format!(", {v}")))
+        .collect()
+}
+
 /// A struct that helps to collect AlarmReactions and can output them to a CSV file
 pub struct SessionOutput {
     /// The current trial reactions in chronological order
     pub trial_reactions: Vec<TrialReaction>,
     /// The output path to the CSV
     pub output_path: String,
+    /// Information about the session that is written next to the CSV
+    pub metadata: SessionMetadata,
+}
+
+/// Information about how a session was run, such as which devices were used
+///
+/// This is written as TOML next to the CSV, with the extension `.meta.toml`.
+#[derive(Debug, Default, Serialize)]
+pub struct SessionMetadata {
+    pub input_mode: InputMode,
+    /// The total number of seconds the session was paused for, such as while the joystick was
+    /// disconnected
+    pub paused_seconds: f32,
+    /// Problems with the session that might affect its results
+    pub warnings: Vec<String>,
+    /// How closely the model kept to its tick rate
+    pub timing: TickStats,
+    /// Every change to the gamepad used for tracking, in chronological order
+    pub devices: Vec<DeviceEvent>,
 }
 
 /// Information about a user's response to an instance of an alarm being fired, a trial executing
 #[derive(Debug, Clone, new)]
 pub struct TrialReaction {
     pub trial_num: usize,
-    /// The reaction time to the alarm in milliseconds
-    pub millis: u32,
+    /// The reaction time to the alarm in milliseconds, which is empty for a miss
+    pub millis: Option<u32>,
     /// If the correct key to respond to the alarm with was pressed or not
     pub correct_key: bool,
-    /// The correct key that should have been pressed
-    pub key: char,
-    /// The root-mean-square error of the distance from the ball to the center crosshair
-    pub rms_error: Vec<f32>,
+    /// The key or button that was pressed in response to the alarm, which is empty for a miss
+    pub key: Option<ResponseInput>,
+    /// The squared distance from the ball to the center crosshair, at every tick of the trial
+    pub squared_distances: Vec<f32>,
+    /// The adaptive difficulty level at every tick of the trial, which is empty when the
+    /// difficulty isn't adaptive
+    pub levels: Vec<f32>,
+    /// The tracking error over each window of the trial
+    pub metrics: TrialMetrics,
 }
 
 impl SessionOutput {
@@ -32,6 +98,7 @@ impl SessionOutput {
         Self {
             trial_reactions: Vec::new(),
             output_path,
+            metadata: SessionMetadata::default(),
         }
     }
 
@@ -40,46 +107,88 @@ impl SessionOutput {
         self.trial_reactions.push(reaction);
     }
 
-    /// Writes all of the currently held TrialReactions to the SessionOutput's path in CSV format
+    /// The path the session metadata is written to, next to the CSV
+    pub fn metadata_path(&self) -> String {
+        Path::new(&self.output_path)
+            .with_extension("meta.toml")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Writes all of the currently held TrialReactions to the SessionOutput's path in CSV format,
+    /// and the session metadata next to it
     pub fn write_to_file(&self) {
+        let metadata_path = self.metadata_path();
+        let written = toml::to_string(&self.metadata)
+            .map_err(anyhow::Error::from)
+            .and_then(|metadata| Ok(std::fs::write(&metadata_path, metadata)?));
+
+        if let Err(e) = written {
+            log::error!("failed to write session metadata to {metadata_path}: {e}");
+        }
+
         let mut file = std::fs::OpenOptions::new()
             .create(true)
             .write(true)
+            .truncate(true)
             .open(self.output_path.clone())
             .unwrap();
 
         let trial_reactions = self.trial_reactions.clone();
 
         std::thread::spawn(move || {
-            write!(file, "{CSV_HEADERS}").unwrap();
-            print!("{CSV_HEADERS}");
+            let metric_headers = metric_headers();
+            write!(file, "{CSV_HEADERS}{metric_headers}").unwrap();
+            print!("{CSV_HEADERS}{metric_headers}");
 
             for t in 0..trial_reactions.len() {
-                write!(file, ", trial {} rmse", t + 1).unwrap();
-                print!(", trial {} rmse", t + 1);
+                write!(file, ", trial {} squared distance", t + 1).unwrap();
+                print!(", trial {} squared distance", t + 1);
+            }
+
+            // The adaptive difficulty levels follow the squared distances, tick for tick
+            let has_levels = trial_reactions.iter().any(|r| !r.levels.is_empty());
+
+            if has_levels {
+                for t in 0..trial_reactions.len() {
+                    write!(file, ", trial {} level", t + 1).unwrap();
+                    print!(", trial {} level", t + 1);
+                }
             }
 
             writeln!(file).unwrap();
             println!();
 
-            let mut rms_errors: Vec<_> =
-                trial_reactions.iter().map(|r| r.rms_error.iter()).collect();
+            let mut squared_distances: Vec<_> = trial_reactions
+                .iter()
+                .map(|r| r.squared_distances.iter())
+                .collect();
+
+            if has_levels {
+                squared_distances.extend(trial_reactions.iter().map(|r| r.levels.iter()));
+            }
 
             for reaction in &trial_reactions {
+                let metrics = metric_columns(&reaction.metrics);
+
+                // A miss leaves the response time and key empty
+                let millis = reaction.millis.map_or(String::new(), |m| m.to_string());
+                let key = reaction.key.map_or(String::new(), |k| k.to_string());
+
                 write!(
                     file,
-                    "{}, {}, {}, {}",
-                    reaction.trial_num, reaction.millis, reaction.correct_key, reaction.key
+                    "{}, {millis}, {}, {key}{metrics}",
+                    reaction.trial_num, reaction.correct_key
                 )
                 .unwrap();
 
                 print!(
-                    "{}, {}, {}, {}",
-                    reaction.trial_num, reaction.millis, reaction.correct_key, reaction.key
+                    "{}, {millis}, {}, {key}{metrics}",
+                    reaction.trial_num, reaction.correct_key
                 );
 
-                for rmse_entry in rms_errors.iter_mut() {
-                    if let Some(entry) = rmse_entry.next() {
+                for distances in squared_distances.iter_mut() {
+                    if let Some(entry) = distances.next() {
                         write!(file, ", {}", entry).unwrap();
                         print!(", {}", entry);
                     } else {
@@ -92,16 +201,18 @@ impl SessionOutput {
                 println!();
             }
 
+            // The rest of the squared distances go in rows of their own, below the trials
+            let blank_columns = ",".repeat(3 + metric_headers.matches(',').count());
             let mut is_done = false;
 
             while !is_done {
                 is_done = true;
 
-                write!(file, ",,,").unwrap();
-                print!(",,,");
+                write!(file, "{blank_columns}").unwrap();
+                print!("{blank_columns}");
 
-                for rmse_entry in rms_errors.iter_mut() {
-                    if let Some(entry) = rmse_entry.next() {
+                for distances in squared_distances.iter_mut() {
+                    if let Some(entry) = distances.next() {
                         write!(file, ", {}", entry).unwrap();
                         print!(", {}", entry);
                         is_done = false;
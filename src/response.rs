@@ -0,0 +1,281 @@
+use derive_new::new;
+use eframe::egui::Key;
+use gilrs::Button;
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Instant};
+
+/// Every keyboard key that can be used as a response
+///
+/// Numpad digits are reported as the same keys as the digits on the main row.
+const RESPONSE_KEYS: [Key; 73] = [
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::ArrowUp,
+    Key::Escape,
+    Key::Tab,
+    Key::Backspace,
+    Key::Enter,
+    Key::Space,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Minus,
+    Key::PlusEquals,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+];
+
+/// Every gamepad button that can be used as a response, along with its name in the config
+const RESPONSE_BUTTONS: [(Button, &str); 19] = [
+    (Button::South, "South"),
+    (Button::East, "East"),
+    (Button::North, "North"),
+    (Button::West, "West"),
+    (Button::C, "C"),
+    (Button::Z, "Z"),
+    (Button::LeftTrigger, "LeftTrigger"),
+    (Button::LeftTrigger2, "LeftTrigger2"),
+    (Button::RightTrigger, "RightTrigger"),
+    (Button::RightTrigger2, "RightTrigger2"),
+    (Button::Select, "Select"),
+    (Button::Start, "Start"),
+    (Button::Mode, "Mode"),
+    (Button::LeftThumb, "LeftThumb"),
+    (Button::RightThumb, "RightThumb"),
+    (Button::DPadUp, "DPadUp"),
+    (Button::DPadDown, "DPadDown"),
+    (Button::DPadLeft, "DPadLeft"),
+    (Button::DPadRight, "DPadRight"),
+];
+
+/// The prefix that marks a gamepad button in the config, such as `gamepad:South`
+const GAMEPAD_PREFIX: &str = "gamepad:";
+
+/// An input that a participant can respond to an alarm with
+///
+/// In the config this is written as the name of a key, such as `"A"`, `"7"`, `"Space"`, `"Up"` or
+/// `"F5"`, or as a gamepad button, such as `"gamepad:South"`. Names are case insensitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ResponseInput {
+    /// A key on the keyboard
+    Key(Key),
+    /// A button on a gamepad or joystick, through [`gilrs`]
+    GamepadButton(Button),
+}
+
+/// A key or button press that might respond to an alarm, along with when it happened
+#[derive(Debug, Clone, Copy, new)]
+pub struct ResponseEvent {
+    pub input: ResponseInput,
+    /// When the press was received, which reaction times are measured from
+    pub time: Instant,
+}
+
+impl ResponseInput {
+    /// Returns the response for a digit key from 0 to 9
+    pub fn digit(digit: u32) -> Option<Self> {
+        let name = char::from_digit(digit, 10)?.to_string();
+        name.parse().ok()
+    }
+}
+
+impl std::str::FromStr for ResponseInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+
+        if let Some(button_name) = name
+            .get(..GAMEPAD_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(GAMEPAD_PREFIX))
+            .map(|_| &name[GAMEPAD_PREFIX.len()..])
+        {
+            return RESPONSE_BUTTONS
+                .iter()
+                .find(|(_, n)| n.eq_ignore_ascii_case(button_name))
+                .map(|(button, _)| ResponseInput::GamepadButton(*button))
+                .ok_or_else(|| {
+                    let names: Vec<_> = RESPONSE_BUTTONS.iter().map(|(_, n)| *n).collect();
+                    format!("`{s}` is not a gamepad button\nAvailable buttons are: {names:?}")
+                });
+        }
+
+        // Also accept the names of egui's variants, such as `ArrowUp` and `Num1`, and `Numpad1`
+        // which is reported as the same key as `1`
+        let lower = name.to_ascii_lowercase();
+        let name = lower
+            .strip_prefix("arrow")
+            .or_else(|| lower.strip_prefix("numpad").filter(|d| d.len() == 1))
+            .or_else(|| lower.strip_prefix("num").filter(|d| d.len() == 1))
+            .unwrap_or(&lower);
+
+        RESPONSE_KEYS
+            .iter()
+            .find(|k| k.name().eq_ignore_ascii_case(name))
+            .map(|k| ResponseInput::Key(*k))
+            .ok_or_else(|| {
+                format!(
+                    "`{s}` is not a supported response\nAvailable keys are: A-Z, 0-9, Space, Enter, Tab, Backspace, Escape, Insert, Delete, Home, End, PageUp, PageDown, Minus, Plus, Up, Down, Left, Right and F1-F20, and gamepad buttons are written as `gamepad:South`"
+                )
+            })
+    }
+}
+
+impl TryFrom<String> for ResponseInput {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ResponseInput> for String {
+    fn from(value: ResponseInput) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for ResponseInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseInput::Key(key) => write!(f, "{}", key.name()),
+            ResponseInput::GamepadButton(button) => {
+                let name = RESPONSE_BUTTONS
+                    .iter()
+                    .find(|(b, _)| b == button)
+                    .map_or("Unknown", |(_, n)| n);
+                write!(f, "{GAMEPAD_PREFIX}{name}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_response_survives_a_round_trip() {
+        let keys = RESPONSE_KEYS.iter().map(|&key| ResponseInput::Key(key));
+        let buttons = RESPONSE_BUTTONS
+            .iter()
+            .map(|&(button, _)| ResponseInput::GamepadButton(button));
+
+        for input in keys.chain(buttons) {
+            let name = input.to_string();
+
+            assert_eq!(name.parse(), Ok(input), "`{name}`");
+            assert_eq!(name.to_lowercase().parse(), Ok(input), "`{name}`");
+        }
+    }
+
+    #[test]
+    fn other_key_names_are_accepted() {
+        let names = [
+            ("Numpad7", Key::Num7),
+            ("num7", Key::Num7),
+            ("7", Key::Num7),
+            ("ArrowUp", Key::ArrowUp),
+            ("up", Key::ArrowUp),
+            (" space ", Key::Space),
+            ("f20", Key::F20),
+        ];
+
+        for (name, key) in names {
+            assert_eq!(name.parse(), Ok(ResponseInput::Key(key)), "`{name}`");
+        }
+    }
+
+    #[test]
+    fn gamepad_buttons_are_written_with_a_prefix() {
+        let south = ResponseInput::GamepadButton(Button::South);
+
+        assert_eq!(south.to_string(), "gamepad:South");
+        assert_eq!("GAMEPAD:south".parse(), Ok(south));
+        assert_eq!(
+            "gamepad:DPadLeft".parse(),
+            Ok(ResponseInput::GamepadButton(Button::DPadLeft))
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        for name in [
+            "",
+            "gamepad:",
+            "gamepad:Jump",
+            "South",
+            "Numpad12",
+            "F21",
+            "Ctrl",
+        ] {
+            assert!(name.parse::<ResponseInput>().is_err(), "`{name}`");
+        }
+    }
+
+    #[test]
+    fn digits_are_keys() {
+        assert_eq!(ResponseInput::digit(0), Some(ResponseInput::Key(Key::Num0)));
+        assert_eq!(ResponseInput::digit(9), Some(ResponseInput::Key(Key::Num9)));
+        assert_eq!(ResponseInput::digit(10), None);
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Settings for how often the model updates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ConfigTiming {
+    /// How many times per second the dials and ball are updated
+    pub tick_rate: f32,
+
+    /// Ticks that run more than this many milliseconds after they were due are counted as late,
+    /// and the session output warns about them
+    pub late_tick_threshold: f32,
+}
+
+impl Default for ConfigTiming {
+    fn default() -> Self {
+        Self {
+            tick_rate: 500.0,
+            late_tick_threshold: 5.0,
+        }
+    }
+}
+
+impl ConfigTiming {
+    /// The length of one tick
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.tick_rate as f64)
+    }
+}
+
+/// Statistics about how closely the model kept to its tick rate
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct TickStats {
+    pub ticks: u64,
+    /// The number of ticks that ran later than the threshold after they were due
+    pub late_ticks: u64,
+    /// How late the latest tick ran, in milliseconds
+    pub max_late_ms: f32,
+    /// How late ticks ran on average, in milliseconds
+    pub mean_late_ms: f32,
+    /// Milliseconds of simulation that were skipped because the model fell too far behind to catch
+    /// up
+    pub dropped_ms: f32,
+    #[serde(skip)]
+    total_late_ms: f64,
+}
+
+impl TickStats {
+    /// Records a tick that ran `lateness` after it was due
+    pub fn record(&mut self, lateness: Duration, timing: &ConfigTiming) {
+        let late_ms = lateness.as_secs_f32() * 1000.0;
+
+        self.ticks += 1;
+        self.total_late_ms += late_ms as f64;
+        self.mean_late_ms = (self.total_late_ms / self.ticks as f64) as f32;
+        self.max_late_ms = self.max_late_ms.max(late_ms);
+
+        if late_ms > timing.late_tick_threshold {
+            self.late_ticks += 1;
+        }
+    }
+
+    /// Records simulation time that was skipped
+    pub fn drop_time(&mut self, dropped: Duration) {
+        self.dropped_ms += dropped.as_secs_f32() * 1000.0;
+    }
+
+    /// A warning for the session output if any ticks were late or skipped
+    pub fn warning(&self, timing: &ConfigTiming) -> Option<String> {
+        if self.late_ticks == 0 && self.dropped_ms == 0.0 {
+            return None;
+        }
+
+        Some(format!(
+            "{} of {} ticks ran more than {}ms late, the latest by {:.1}ms, and {:.1}ms of simulation was skipped",
+            self.late_ticks, self.ticks, timing.late_tick_threshold, self.max_late_ms, self.dropped_ms
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(ms: f32) -> Duration {
+        Duration::from_secs_f32(ms / 1000.0)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{actual} isn't {expected}"
+        );
+    }
+
+    #[test]
+    fn ticks_are_late_past_the_threshold() {
+        let timing = ConfigTiming::default();
+        let mut stats = TickStats::default();
+
+        for lateness in [0.0, 2.0, 5.0, 7.0, 11.0] {
+            stats.record(millis(lateness), &timing);
+        }
+
+        assert_eq!(stats.ticks, 5);
+        // Exactly on the threshold isn't late
+        assert_eq!(stats.late_ticks, 2);
+        assert_close(stats.max_late_ms, 11.0);
+        assert_close(stats.mean_late_ms, 5.0);
+    }
+
+    #[test]
+    fn on_time_sessions_have_no_warning() {
+        let timing = ConfigTiming::default();
+        let mut stats = TickStats::default();
+
+        for _ in 0..100 {
+            stats.record(millis(1.0), &timing);
+        }
+
+        assert_eq!(stats.warning(&timing), None);
+    }
+
+    #[test]
+    fn late_or_skipped_ticks_are_warned_about() {
+        let timing = ConfigTiming::default();
+
+        let mut late = TickStats::default();
+        late.record(millis(1.0), &timing);
+        late.record(millis(8.0), &timing);
+        let warning = late.warning(&timing).expect("a tick was late");
+        assert!(
+            warning.starts_with("1 of 2 ticks ran more than 5ms late"),
+            "{warning}"
+        );
+
+        let mut skipped = TickStats::default();
+        skipped.record(millis(1.0), &timing);
+        skipped.drop_time(millis(250.0));
+        let warning = skipped.warning(&timing).expect("time was skipped");
+        assert!(
+            warning.ends_with("250.0ms of simulation was skipped"),
+            "{warning}"
+        );
+    }
+}
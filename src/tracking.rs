@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::tracking_widget::ConfigAppearance;
+
+/// What the participant keeps the ball on
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackingMode {
+    /// Compensatory tracking: the disturbance moves the ball, which is kept on a fixed crosshair in
+    /// the center of the frame
+    #[default]
+    Compensatory,
+    /// Pursuit tracking: the disturbance moves a target around the frame, and the ball is a
+    /// cursor that only the participant moves, which is kept on the target
+    Pursuit,
+}
+
+/// Settings for the tracking task
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+#[serde(default)]
+pub struct ConfigTracking {
+    /// [`TrackingMode`]
+    pub mode: TrackingMode,
+
+    /// How the tracking frame looks
+    pub appearance: ConfigAppearance,
+}
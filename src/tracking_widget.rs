@@ -6,21 +6,114 @@ use eframe::{
 };
 use serde::{Deserialize, Serialize};
 
-const FRAME_BORDER_WIDTH: f32 = 1.0;
-const FRAME_BORDER_COLOR: Color32 = Color32::WHITE;
+use crate::ball::BallAxis;
 
 const FRAME_MAX_HEIGHT_PERCENT: f32 = 1.0;
 const FRAME_MAX_WIDTH_PERCENT: f32 = 0.7;
 
-const CROSSHAIR_STROKE: f32 = 1.0;
-const CROSSHAIR_COLOR: Color32 = Color32::WHITE;
-
-const BALL_RADIUS: f32 = 0.03;
+/// The line the ball moves along in one-dimensional tracking
+const TRACK_STROKE: f32 = 1.0;
+const TRACK_COLOR: Color32 = Color32::DARK_GRAY;
 
 ///For how long will the feedback be displayed for
 pub const FEEDBACK_FLASH_TIME: f32 = 1.2;
 
-const BALL_COLOR: egui::Color32 = egui::Color32::LIGHT_GREEN;
+const WARNING_COLOR: Color32 = Color32::YELLOW;
+
+/// A color written in the config as a hex string, like `"#90ee90"`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct HexColor(pub Color32);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let hex = value.strip_prefix('#').unwrap_or(&value);
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+
+        match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(HexColor(Color32::from_rgb(r, g, b))),
+            _ => Err(format!(
+                "`{value}` is not a color, colors are written in hex like \"#90ee90\""
+            )),
+        }
+    }
+}
+
+impl From<HexColor> for String {
+    fn from(value: HexColor) -> Self {
+        let [r, g, b, _] = value.0.to_array();
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// How the crosshair in the center of the frame is drawn
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CrosshairStyle {
+    /// A horizontal and a vertical line
+    #[default]
+    Cross,
+    /// A ring
+    Circle,
+    /// A filled dot
+    Dot,
+    /// Nothing is drawn
+    None,
+}
+
+/// How the tracking frame and everything in it looks
+///
+/// Sizes are relative to the frame, where 1.0 is half of its width, and line widths are in
+/// pixels.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ConfigAppearance {
+    /// The ball's radius, which is also how close its center can get to the edges of the frame
+    pub ball_radius: f32,
+    pub ball_color: HexColor,
+
+    /// [`CrosshairStyle`]
+    pub crosshair_style: CrosshairStyle,
+    /// How far the crosshair reaches from the center, or the radius of a circle or dot
+    pub crosshair_size: f32,
+    pub crosshair_color: HexColor,
+    pub crosshair_width: f32,
+
+    /// The color of the ring drawn around the target, or around the crosshair in compensatory
+    /// tracking, whose radius is the metrics' target radius
+    pub target_ring_color: HexColor,
+    pub target_ring_width: f32,
+
+    /// The color of the frame's border, apart from while it flashes the feedback color
+    pub frame_color: HexColor,
+    pub frame_width: f32,
+    /// The color inside the frame
+    pub background_color: HexColor,
+}
+
+impl Default for ConfigAppearance {
+    fn default() -> Self {
+        Self {
+            ball_radius: 0.03,
+            ball_color: HexColor(Color32::LIGHT_GREEN),
+            crosshair_style: CrosshairStyle::Cross,
+            crosshair_size: 0.03,
+            crosshair_color: HexColor(Color32::WHITE),
+            crosshair_width: 1.0,
+            target_ring_color: HexColor(Color32::WHITE),
+            target_ring_width: 2.0,
+            frame_color: HexColor(Color32::WHITE),
+            frame_width: 1.0,
+            // The same as the rest of the window
+            background_color: HexColor(Color32::from_rgb(27, 27, 27)),
+        }
+    }
+}
 
 //The three possible colors for the Box to have, excluding the default WHITE.
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -47,9 +140,25 @@ impl From<FeedbackColor> for Color32 {
 #[derive(new)]
 pub struct TrackingWidget {
     ball_pos: Pos2,
+    /// The target in pursuit tracking, which is drawn instead of the crosshair
+    target_pos: Option<Pos2>,
     key_detected: bool,
     feedback_text: Option<String>,
     outline_color: Color32,
+    /// Shown in the middle of the frame while the session is paused
+    warning: Option<String>,
+    view: TrackingView,
+}
+
+/// The settings for drawing the tracking frame, which don't change during a session
+#[derive(Debug, Clone, Copy, new)]
+pub struct TrackingView {
+    /// The directions the ball can move in
+    pub axis: BallAxis,
+    pub appearance: ConfigAppearance,
+    /// How close the ball has to be to the target to count as on target, which is the radius of
+    /// the ring drawn around it
+    pub target_radius: f32,
 }
 
 //This structure communicates with AppState in order to get the information needed.
@@ -59,12 +168,14 @@ pub struct TrackingWidgetState {
     pub feedback_text: Option<String>,
     time_since: f32,
     pub outline_color: Color32,
+    /// The color the frame's border returns to after flashing
+    frame_color: Color32,
 }
 
 impl TrackingWidgetState {
     pub fn blink(&mut self, feedback_text: Option<&str>, respond_color: Option<FeedbackColor>) {
         self.key_detected = true;
-        self.outline_color = respond_color.map_or(FRAME_BORDER_COLOR, |c| c.into());
+        self.outline_color = respond_color.map_or(self.frame_color, |c| c.into());
         self.feedback_text = feedback_text.map(|s| s.to_string());
     }
 
@@ -75,7 +186,7 @@ impl TrackingWidgetState {
             if self.time_since >= FEEDBACK_FLASH_TIME {
                 self.time_since = 0.0;
                 self.key_detected = false;
-                self.outline_color = FRAME_BORDER_COLOR;
+                self.outline_color = self.frame_color;
             }
         }
     }
@@ -95,20 +206,21 @@ impl TrackingWidget {
         // Only draw if we need to
         if ui.is_rect_visible(rect) {
             let painter = ui.painter();
+            let appearance = &self.view.appearance;
 
             // Draw the frame
-            let frame_shape = egui::epaint::RectShape::stroke(
+            let frame_shape = egui::epaint::RectShape {
                 rect,
-                egui::Rounding::none(),
-                egui::Stroke::new(FRAME_BORDER_WIDTH, self.outline_color),
-            );
+                rounding: egui::Rounding::none(),
+                fill: appearance.background_color.0,
+                stroke: egui::Stroke::new(appearance.frame_width, self.outline_color),
+            };
 
             painter.add(egui::Shape::Rect(frame_shape));
 
-            // Draw the crosshair
             // The frame is guaranteed to be square
             let frame_width = rect.width();
-            let crosshair_half_size = BALL_RADIUS * frame_width / 2.0;
+            let half_frame_width = frame_width / 2.0;
             let center = rect.center();
 
             // Draw feedback text
@@ -122,31 +234,97 @@ impl TrackingWidget {
                 painter.text(text_pos, anchor, text, font_id, text_color);
             }
 
-            let v_top_pos = Pos2::new(center.x, center.y - crosshair_half_size);
-            let v_bottom_pos = Pos2::new(center.x, center.y + crosshair_half_size);
+            if let Some(warning) = &self.warning {
+                let text_pos = Pos2::new(center.x, rect.top() + rect.height() * 0.25);
+                let font_id = FontId::proportional(30.0);
 
-            let h_left_pos = Pos2::new(center.x - crosshair_half_size, center.y);
-            let h_right_pos = Pos2::new(center.x + crosshair_half_size, center.y);
+                painter.text(
+                    text_pos,
+                    Align2::CENTER_CENTER,
+                    warning,
+                    font_id,
+                    WARNING_COLOR,
+                );
+            }
 
-            let stroke = egui::Stroke::new(CROSSHAIR_STROKE, CROSSHAIR_COLOR);
+            // Draw the track the ball moves along, across the whole frame
+            if let Some(direction) = self.view.axis.direction() {
+                let half_track = direction * half_frame_width;
 
-            painter.line_segment([v_top_pos, v_bottom_pos], stroke);
-            painter.line_segment([h_left_pos, h_right_pos], stroke);
+                painter.line_segment(
+                    [center - half_track, center + half_track],
+                    egui::Stroke::new(TRACK_STROKE, TRACK_COLOR),
+                );
+            }
 
-            // Draw the ball
-            let half_frame_width = frame_width / 2.0;
+            // Draw the ring that the ball is on target inside of, around the target in pursuit
+            // tracking and around the crosshair otherwise
+            let target_center = self.target_pos.map_or(center, |target_pos| {
+                Pos2::new(
+                    center.x + target_pos.x * half_frame_width,
+                    center.y + target_pos.y * half_frame_width,
+                )
+            });
+
+            painter.add(egui::Shape::Circle(CircleShape::stroke(
+                target_center,
+                self.view.target_radius * half_frame_width,
+                egui::Stroke::new(appearance.target_ring_width, appearance.target_ring_color.0),
+            )));
+
+            if self.target_pos.is_none() {
+                // Draw the crosshair
+                let crosshair_half_size = appearance.crosshair_size * half_frame_width;
+                let stroke =
+                    egui::Stroke::new(appearance.crosshair_width, appearance.crosshair_color.0);
+
+                match appearance.crosshair_style {
+                    CrosshairStyle::Cross => {
+                        let v_top_pos = Pos2::new(center.x, center.y - crosshair_half_size);
+                        let v_bottom_pos = Pos2::new(center.x, center.y + crosshair_half_size);
 
+                        let h_left_pos = Pos2::new(center.x - crosshair_half_size, center.y);
+                        let h_right_pos = Pos2::new(center.x + crosshair_half_size, center.y);
+
+                        // Only the line across the track is needed to mark the center of a
+                        // single axis
+                        if self.view.axis != BallAxis::Vertical {
+                            painter.line_segment([v_top_pos, v_bottom_pos], stroke);
+                        }
+                        if self.view.axis != BallAxis::Horizontal {
+                            painter.line_segment([h_left_pos, h_right_pos], stroke);
+                        }
+                    }
+                    CrosshairStyle::Circle => {
+                        painter.add(egui::Shape::Circle(CircleShape::stroke(
+                            center,
+                            crosshair_half_size,
+                            stroke,
+                        )));
+                    }
+                    CrosshairStyle::Dot => {
+                        painter.add(egui::Shape::Circle(CircleShape::filled(
+                            center,
+                            crosshair_half_size,
+                            appearance.crosshair_color.0,
+                        )));
+                    }
+                    CrosshairStyle::None => {}
+                }
+            }
+
+            // Draw the ball
             let ball_center = Pos2::new(
                 center.x + self.ball_pos.x * half_frame_width,
                 center.y + self.ball_pos.y * half_frame_width,
             );
 
-            let ball_pixel_radius = BALL_RADIUS * half_frame_width;
+            let ball_pixel_radius = appearance.ball_radius * half_frame_width;
 
             painter.add(egui::Shape::Circle(CircleShape::filled(
                 ball_center,
                 ball_pixel_radius,
-                BALL_COLOR,
+                appearance.ball_color.0,
             )));
         }
 
@@ -0,0 +1,198 @@
+use std::{
+    ffi::CString,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use eframe::egui;
+use egui_glow::{egui_winit::winit, glow, EguiGlow};
+use glutin::{
+    config::ConfigTemplateBuilder,
+    context::{ContextApi, ContextAttributesBuilder},
+    display::GetGlDisplay,
+    prelude::*,
+    surface::SwapInterval,
+};
+use glutin_winit::{ApiPrefence, DisplayBuilder, GlWindow};
+use raw_window_handle::HasRawWindowHandle;
+use winit::{
+    dpi::LogicalSize,
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoopBuilder},
+    platform::run_return::EventLoopExtRunReturn,
+    window::{CursorGrabMode, WindowBuilder},
+};
+
+use crate::app::DialsApp;
+
+/// How often the window is redrawn when the monitor doesn't say how often it refreshes
+const DEFAULT_REFRESH_RATE: f64 = 60.0;
+
+/// Opens the session window and runs it until it is closed
+///
+/// This drives winit directly instead of going through [`eframe`], which only hands input to the
+/// app once per frame. Here every winit event is passed to the app as soon as it arrives, along
+/// with when it arrived, so that key presses are timed from the event rather than the frame.
+///
+/// If `lock_pointer` is set, the mouse pointer is kept inside the window while it is focused.
+pub fn run(
+    title: &str,
+    lock_pointer: bool,
+    create_app: impl FnOnce(&egui::Context) -> DialsApp,
+) -> Result<()> {
+    let mut event_loop = EventLoopBuilder::new().build();
+
+    let window_builder = WindowBuilder::new()
+        .with_title(title)
+        .with_maximized(true)
+        .with_inner_size(LogicalSize::new(1920.0 / 2.0, 1080.0 / 2.0));
+
+    let (window, gl_config) = DisplayBuilder::new()
+        .with_preference(ApiPrefence::FallbackEgl)
+        .with_window_builder(Some(window_builder.clone()))
+        .build(&event_loop, ConfigTemplateBuilder::new(), |mut configs| {
+            configs
+                .next()
+                .expect("the display should have at least one config")
+        })
+        .map_err(|e| anyhow!("Failed to create an OpenGL display: {e}"))?;
+
+    let window = match window {
+        Some(window) => window,
+        None => glutin_winit::finalize_window(&event_loop, window_builder, &gl_config)
+            .context("Failed to create the window")?,
+    };
+
+    let gl_display = gl_config.display();
+    let raw_window_handle = Some(window.raw_window_handle());
+
+    // SAFETY: the window handle is valid, as the window outlives the context and surface
+    let (gl_surface, gl_context) = unsafe {
+        // OpenGL ES is tried if desktop OpenGL isn't available
+        let gl_context = gl_display
+            .create_context(
+                &gl_config,
+                &ContextAttributesBuilder::new().build(raw_window_handle),
+            )
+            .or_else(|_| {
+                gl_display.create_context(
+                    &gl_config,
+                    &ContextAttributesBuilder::new()
+                        .with_context_api(ContextApi::Gles(None))
+                        .build(raw_window_handle),
+                )
+            })
+            .context("Failed to create an OpenGL context")?;
+
+        let gl_surface = gl_display
+            .create_window_surface(
+                &gl_config,
+                &window.build_surface_attributes(Default::default()),
+            )
+            .context("Failed to create an OpenGL surface")?;
+
+        let gl_context = gl_context
+            .make_current(&gl_surface)
+            .context("Failed to make the OpenGL context current")?;
+
+        (gl_surface, gl_context)
+    };
+
+    // Waiting for the screen to refresh when swapping buffers would block the event loop, which
+    // would hold up input events until the next frame. Frames are paced by the event loop instead.
+    if let Err(e) = gl_surface.set_swap_interval(&gl_context, SwapInterval::DontWait) {
+        log::warn!("couldn't turn off vsync, key presses may be timed late: {e}");
+    }
+
+    // SAFETY: the context was made current above, and stays current for the rest of the session
+    let gl = Arc::new(unsafe {
+        glow::Context::from_loader_function(|name| {
+            let name = CString::new(name).unwrap();
+            gl_display.get_proc_address(&name)
+        })
+    });
+
+    let mut egui_glow = EguiGlow::new(&event_loop, gl, None);
+    egui_glow
+        .egui_winit
+        .set_pixels_per_point(window.scale_factor() as f32);
+
+    let mut app = create_app(&egui_glow.egui_ctx);
+
+    let refresh_rate = window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+        .map_or(DEFAULT_REFRESH_RATE, |millihertz| {
+            millihertz as f64 / 1000.0
+        });
+    let frame_time = Duration::from_secs_f64(1.0 / refresh_rate);
+    let mut next_frame = Instant::now();
+
+    event_loop.run_return(|event, _, control_flow| match event {
+        Event::WindowEvent { event, .. } => {
+            // Taken before anything else is done with the event, so that nothing adds to the
+            // reaction time
+            let received = Instant::now();
+
+            match &event {
+                WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                    control_flow.set_exit();
+                }
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    window.resize_surface(&gl_surface, &gl_context);
+                }
+                // Some platforms let go of the pointer when the window loses focus, so it is
+                // locked again whenever the window gets it back
+                WindowEvent::Focused(true) if lock_pointer => {
+                    // Confining keeps the pointer's position, which the ball is controlled by,
+                    // unlike locking it in place, which is all that macOS supports
+                    if let Err(e) = window.set_cursor_grab(CursorGrabMode::Confined) {
+                        log::warn!("couldn't lock the mouse pointer to the window: {e}");
+                    }
+                }
+                _ => {}
+            }
+
+            // egui turns each winit event into any number of its own events, which are added to
+            // the end of the frame's input
+            let first_new = egui_glow.egui_winit.egui_input().events.len();
+            // The window is redrawn every frame anyway, so egui never needs to ask for it
+            let _ = egui_glow.on_event(&event);
+
+            let events = &egui_glow.egui_winit.egui_input().events;
+            if events.len() > first_new {
+                app.on_events(&events[first_new..], received);
+            }
+        }
+        Event::MainEventsCleared if Instant::now() >= next_frame => window.request_redraw(),
+        Event::RedrawRequested(_) => {
+            egui_glow.run(&window, |ctx| app.update(ctx));
+
+            // SAFETY: the context is current, and these only clear the frame
+            unsafe {
+                use glow::HasContext;
+
+                egui_glow.painter.gl().clear_color(0.0, 0.0, 0.0, 1.0);
+                egui_glow.painter.gl().clear(glow::COLOR_BUFFER_BIT);
+            }
+            egui_glow.paint(&window);
+
+            if let Err(e) = gl_surface.swap_buffers(&gl_context) {
+                log::error!("failed to swap buffers: {e}");
+            }
+
+            // Frames keep to the monitor's refresh rate, unless drawing fell behind it
+            next_frame = (next_frame + frame_time).max(Instant::now());
+        }
+        // The event loop sleeps until the next frame is due, but wakes up for any input in the
+        // meantime
+        Event::RedrawEventsCleared if *control_flow != ControlFlow::Exit => {
+            control_flow.set_wait_until(next_frame);
+        }
+        Event::LoopDestroyed => egui_glow.destroy(),
+        _ => {}
+    });
+
+    Ok(())
+}